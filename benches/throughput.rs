@@ -0,0 +1,48 @@
+use chip8::{Backend, Chip8};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+const INSTRUCTIONS: u64 = 100_000;
+
+const ROMS: &[(&str, &[u8])] = &[
+    ("pong", include_bytes!("../roms/pong.ch8")),
+    ("test_opcode", include_bytes!("../roms/test_opcode.ch8")),
+];
+
+// "uncached" decodes every instruction as it runs, the baseline the cache and
+// the recompiler are measured against
+const CONFIGS: &[(&str, Backend, bool)] = &[
+    ("uncached", Backend::Interpreter, false),
+    ("cached", Backend::Interpreter, true),
+    ("recompiler", Backend::Recompiler, true),
+];
+
+fn run(chip8: &mut Chip8) {
+    chip8.run_cycles(INSTRUCTIONS as usize);
+}
+
+fn throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("throughput");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+
+    for &(name, rom) in ROMS {
+        for &(label, backend, cached) in CONFIGS {
+            group.bench_function(BenchmarkId::new(name, label), |b| {
+                b.iter_batched_ref(
+                    || {
+                        let mut chip8 = Chip8::builder().backend(backend).build();
+                        chip8.set_program(rom);
+                        chip8.set_instruction_cache_enabled(cached);
+                        chip8
+                    },
+                    run,
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
@@ -0,0 +1,420 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    ops::Range,
+};
+
+use crate::Instruction;
+
+/// Bytes of a sprite drawn with height 0, a 16x16 SUPER-CHIP sprite
+const LARGE_SPRITE_BYTES: u16 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Falling through to the next instruction, or returning after a call
+    Next,
+    Jump,
+    /// Past the instruction a skip instruction skips
+    Skip,
+    Call,
+    /// `JP V0, nnn` to its base address. Where it really goes depends on V0
+    Computed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub target: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub start: u16,
+    pub instructions: Vec<(u16, Instruction)>,
+    pub edges: Vec<Edge>,
+    /// Sprites drawn from an address set with `LD I, nnn` in this block
+    pub sprites: Vec<Range<u16>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subroutine {
+    pub entry: u16,
+    /// The start of every block reachable from the entry without calls
+    pub blocks: Vec<u16>,
+    pub returns: bool,
+}
+
+/// The code reachable from a ROM's entry point, found by following every
+/// jump, call and skip without running it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlFlowGraph {
+    pub entry_point: u16,
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub subroutines: BTreeMap<u16, Subroutine>,
+    /// Sprite data, merged where it overlaps
+    pub data: Vec<Range<u16>>,
+    /// Targets outside the program or holding no valid instruction
+    pub dead_ends: Vec<u16>,
+}
+
+struct Decoded {
+    instruction: Instruction,
+    edges: Vec<Edge>,
+    ends_block: bool,
+}
+
+impl ControlFlowGraph {
+    pub fn analyze(program: &[u8], entry_point: u16) -> Self {
+        let fetch = |address: u16| {
+            let offset = address.checked_sub(entry_point)? as usize;
+            let bytes = program.get(offset..offset + 2)?;
+            Instruction::decode(u16::from_be_bytes([bytes[0], bytes[1]])).ok()
+        };
+
+        let mut decoded = BTreeMap::new();
+        let mut leaders = BTreeSet::new();
+        let mut calls = BTreeSet::new();
+        let mut dead_ends = BTreeSet::new();
+        leaders.insert(entry_point);
+
+        let mut pending = vec![entry_point];
+        while let Some(address) = pending.pop() {
+            if decoded.contains_key(&address) || dead_ends.contains(&address) {
+                continue;
+            }
+            let instruction = match fetch(address) {
+                Some(instruction) => instruction,
+                None => {
+                    dead_ends.insert(address);
+                    continue;
+                }
+            };
+
+            let next = (address + 2) & 0xfff;
+            let edge = |kind, target| Edge { kind, target };
+            let (edges, ends_block) = match instruction {
+                Instruction::Jp(nnn) => (vec![edge(EdgeKind::Jump, nnn)], true),
+                Instruction::Call(nnn) => {
+                    calls.insert(nnn);
+                    let edges = vec![edge(EdgeKind::Call, nnn), edge(EdgeKind::Next, next)];
+                    (edges, true)
+                }
+                Instruction::Ret => (vec![], true),
+                Instruction::JpV0(nnn) => (vec![edge(EdgeKind::Computed, nnn)], true),
+                Instruction::SeVxByte { .. }
+                | Instruction::SneVxByte { .. }
+                | Instruction::SeVxVy { .. }
+                | Instruction::SneVxVy { .. }
+                | Instruction::Skp { .. }
+                | Instruction::Sknp { .. } => {
+                    let skip = (next + 2) & 0xfff;
+                    let edges = vec![edge(EdgeKind::Next, next), edge(EdgeKind::Skip, skip)];
+                    (edges, true)
+                }
+                _ => (vec![edge(EdgeKind::Next, next)], false),
+            };
+
+            for edge in &edges {
+                pending.push(edge.target);
+                if ends_block {
+                    leaders.insert(edge.target);
+                }
+            }
+            decoded.insert(
+                address,
+                Decoded {
+                    instruction,
+                    edges,
+                    ends_block,
+                },
+            );
+        }
+
+        let blocks: BTreeMap<u16, BasicBlock> = leaders
+            .iter()
+            .filter(|leader| decoded.contains_key(leader))
+            .map(|&leader| (leader, block(leader, &decoded, &leaders)))
+            .collect();
+
+        let subroutines = calls
+            .iter()
+            .filter(|entry| blocks.contains_key(entry))
+            .map(|&entry| {
+                let reachable = reachable(&blocks, entry);
+                let returns = reachable.iter().any(|start| {
+                    let last = blocks[start].instructions.last();
+                    matches!(last, Some((_, Instruction::Ret)))
+                });
+                let subroutine = Subroutine {
+                    entry,
+                    blocks: reachable.into_iter().collect(),
+                    returns,
+                };
+                (entry, subroutine)
+            })
+            .collect();
+
+        let mut sprites: Vec<Range<u16>> = blocks
+            .values()
+            .flat_map(|block| block.sprites.iter().cloned())
+            .collect();
+        sprites.sort_by_key(|range| range.start);
+        let mut data: Vec<Range<u16>> = Vec::new();
+        for range in sprites {
+            match data.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => data.push(range),
+            }
+        }
+
+        Self {
+            entry_point,
+            blocks,
+            subroutines,
+            data,
+            dead_ends: dead_ends.into_iter().collect(),
+        }
+    }
+
+    /// The graph in Graphviz DOT, with a cluster for each subroutine and
+    /// dashed edges to the sprites a block draws
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        // A block shared by several subroutines is drawn in the first one
+        let mut drawn = BTreeSet::new();
+        for subroutine in self.subroutines.values() {
+            writeln!(dot, "    subgraph cluster_{:03x} {{", subroutine.entry).unwrap();
+            writeln!(dot, "        label=\"sub {:#05x}\";", subroutine.entry).unwrap();
+            for start in &subroutine.blocks {
+                if drawn.insert(*start) {
+                    writeln!(dot, "        {}", self.node(&self.blocks[start])).unwrap();
+                }
+            }
+            writeln!(dot, "    }}").unwrap();
+        }
+        for block in self.blocks.values() {
+            if !drawn.contains(&block.start) {
+                writeln!(dot, "    {}", self.node(block)).unwrap();
+            }
+        }
+        for address in &self.dead_ends {
+            writeln!(
+                dot,
+                "    b{:03x} [shape=octagon, label=\"{:#05x} ?\"];",
+                address, address
+            )
+            .unwrap();
+        }
+        for range in &self.data {
+            writeln!(
+                dot,
+                "    d{:03x} [shape=note, label=\"data {:#05x}-{:#05x}\"];",
+                range.start,
+                range.start,
+                range.end - 1
+            )
+            .unwrap();
+        }
+
+        for block in self.blocks.values() {
+            for edge in &block.edges {
+                let attributes = match edge.kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Skip => " [label=\"skip\"]",
+                    EdgeKind::Call => " [label=\"call\", style=bold]",
+                    EdgeKind::Computed => " [label=\"+V0\", style=dotted]",
+                };
+                writeln!(
+                    dot,
+                    "    b{:03x} -> b{:03x}{};",
+                    block.start, edge.target, attributes
+                )
+                .unwrap();
+            }
+            for sprite in &block.sprites {
+                let region = self.data.iter().find(|range| range.contains(&sprite.start));
+                writeln!(
+                    dot,
+                    "    b{:03x} -> d{:03x} [style=dashed];",
+                    block.start,
+                    region.unwrap().start
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    fn node(&self, block: &BasicBlock) -> String {
+        let mut label = String::new();
+        for (address, instruction) in &block.instructions {
+            write!(label, "{:#05x}  {}\\l", address, instruction).unwrap();
+        }
+        let shape = if block.start == self.entry_point {
+            ", style=bold"
+        } else {
+            ""
+        };
+        format!("b{:03x} [label=\"{}\"{}];", block.start, label, shape)
+    }
+}
+
+/// Runs from a leader up to the next control flow instruction or leader.
+/// I is only followed within the block
+fn block(leader: u16, decoded: &BTreeMap<u16, Decoded>, leaders: &BTreeSet<u16>) -> BasicBlock {
+    let mut block = BasicBlock {
+        start: leader,
+        instructions: Vec::new(),
+        edges: Vec::new(),
+        sprites: Vec::new(),
+    };
+
+    let mut i = None;
+    let mut address = leader;
+    loop {
+        let current = &decoded[&address];
+        block.instructions.push((address, current.instruction));
+        match current.instruction {
+            Instruction::LdI(nnn) => i = Some(nnn),
+            Instruction::Drw { n, .. } => {
+                if let Some(i) = i {
+                    let len = if n == 0 { LARGE_SPRITE_BYTES } else { n as u16 };
+                    block.sprites.push(i..i + len);
+                }
+            }
+            Instruction::AddIVx { .. }
+            | Instruction::LdFVx { .. }
+            | Instruction::LdIVx { .. }
+            | Instruction::LdVxI { .. } => i = None,
+            _ => {}
+        }
+
+        let next = (address + 2) & 0xfff;
+        if current.ends_block || leaders.contains(&next) || !decoded.contains_key(&next) {
+            block.edges = current.edges.clone();
+            return block;
+        }
+        address = next;
+    }
+}
+
+fn reachable(blocks: &BTreeMap<u16, BasicBlock>, entry: u16) -> BTreeSet<u16> {
+    let mut reachable = BTreeSet::new();
+    let mut pending = vec![entry];
+    while let Some(start) = pending.pop() {
+        if !reachable.insert(start) {
+            continue;
+        }
+        for edge in &blocks[&start].edges {
+            if edge.kind != EdgeKind::Call && blocks.contains_key(&edge.target) {
+                pending.push(edge.target);
+            }
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::PONG;
+
+    // Count V0 up to 3 with a subroutine that draws a sprite, then stop
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 23] = [
+        0x60, 0x00, // 0x200  LD V0, 0
+        0x22, 0x0c, // 0x202  CALL 0x20c
+        0x30, 0x03, // 0x204  SE V0, 3
+        0x12, 0x02, // 0x206  JP 0x202
+        0x12, 0x08, // 0x208  JP 0x208
+        0x00, 0x00, // 0x20a
+        0xa2, 0x14, // 0x20c  LD I, 0x214
+        0xd0, 0x03, // 0x20e  DRW V0, V0, 3
+        0x70, 0x01, // 0x210  ADD V0, 1
+        0x00, 0xee, // 0x212  RET
+        0xf0, 0x90, 0xf0,
+    ];
+
+    fn edge(kind: EdgeKind, target: u16) -> Edge {
+        Edge { kind, target }
+    }
+
+    #[test]
+    fn blocks_and_edges() {
+        let cfg = ControlFlowGraph::analyze(&PROGRAM, 0x200);
+
+        let starts: Vec<u16> = cfg.blocks.keys().copied().collect();
+        assert_eq!(starts, [0x200, 0x202, 0x204, 0x206, 0x208, 0x20c]);
+        assert_eq!(cfg.blocks[&0x200].edges, [edge(EdgeKind::Next, 0x202)]);
+        assert_eq!(
+            cfg.blocks[&0x202].edges,
+            [edge(EdgeKind::Call, 0x20c), edge(EdgeKind::Next, 0x204)]
+        );
+        assert_eq!(
+            cfg.blocks[&0x204].edges,
+            [edge(EdgeKind::Next, 0x206), edge(EdgeKind::Skip, 0x208)]
+        );
+        assert_eq!(cfg.blocks[&0x20c].instructions.len(), 4);
+        assert!(cfg.blocks[&0x20c].edges.is_empty());
+        assert!(cfg.dead_ends.is_empty());
+    }
+
+    #[test]
+    fn subroutines_and_data() {
+        let cfg = ControlFlowGraph::analyze(&PROGRAM, 0x200);
+
+        let subroutines: Vec<&Subroutine> = cfg.subroutines.values().collect();
+        assert_eq!(
+            subroutines,
+            [&Subroutine {
+                entry: 0x20c,
+                blocks: vec![0x20c],
+                returns: true,
+            }]
+        );
+        assert_eq!(cfg.blocks[&0x20c].sprites, vec![0x214..0x217]);
+        assert_eq!(cfg.data, cfg.blocks[&0x20c].sprites);
+    }
+
+    #[test]
+    fn computed_jumps_and_dead_ends() {
+        // JP V0, 0x204; JP 0x300; JP 0x206; then an invalid instruction
+        let program = [0xb2, 0x04, 0x13, 0x00, 0x12, 0x06, 0x01, 0x23];
+        let cfg = ControlFlowGraph::analyze(&program, 0x200);
+
+        assert_eq!(cfg.blocks[&0x200].edges, [edge(EdgeKind::Computed, 0x204)]);
+        assert!(!cfg.blocks.contains_key(&0x202));
+        assert_eq!(cfg.dead_ends, [0x206]);
+    }
+
+    #[test]
+    fn dot() {
+        let dot = ControlFlowGraph::analyze(&PROGRAM, 0x200).to_dot();
+
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains(
+            "    subgraph cluster_20c {\n        label=\"sub 0x20c\";\n        b20c [label=\"0x20c  LD I, 0x214\\l"
+        ));
+        assert!(dot.contains("    b200 [label=\"0x200  LD V0, 0x00\\l\", style=bold];\n"));
+        assert!(dot.contains("    b202 -> b20c [label=\"call\", style=bold];\n"));
+        assert!(dot.contains("    b204 -> b208 [label=\"skip\"];\n"));
+        assert!(dot.contains("    b20c -> d214 [style=dashed];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn pong() {
+        let program = PONG;
+        let cfg = ControlFlowGraph::analyze(program, 0x200);
+
+        assert!(cfg.dead_ends.is_empty());
+        assert!(cfg.subroutines[&0x2d4].returns);
+        assert!(cfg.data.iter().any(|range| range.contains(&0x2ea)));
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    f32::consts::TAU,
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::Path,
+};
+
+use crate::FRAME_RATE;
+
+/// An audio output. It's told once per emulated frame whether the sound timer
+/// is running
+pub trait Audio {
+    fn set_sound_on(&mut self, on: bool);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    Square,
+    #[default]
+    Sine,
+    Triangle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub waveform: Waveform,
+    /// In Hz
+    pub frequency: f32,
+    /// From 0 to 1
+    pub volume: f32,
+    /// Seconds to fade in at the start of a beep
+    pub attack: f32,
+    /// Seconds to fade out at the end of a beep
+    pub release: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::default(),
+            frequency: 440.0,
+            volume: 0.5,
+            attack: 0.002,
+            release: 0.005,
+        }
+    }
+}
+
+/// Produces mono PCM samples of the beep. Starting and stopping goes through
+/// short ramps, so the output never jumps and doesn't pop
+pub struct SampleGenerator {
+    tone: Tone,
+    sample_rate: u32,
+    sound_on: bool,
+    phase: f32,
+    level: f32,
+}
+
+impl SampleGenerator {
+    pub fn new(tone: Tone, sample_rate: u32) -> Self {
+        Self {
+            tone,
+            sample_rate,
+            sound_on: false,
+            phase: 0.0,
+            level: 0.0,
+        }
+    }
+
+    pub fn tone(&self) -> Tone {
+        self.tone
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.tone = tone;
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn is_sound_on(&self) -> bool {
+        self.sound_on
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let rate = self.sample_rate as f32;
+        if self.sound_on {
+            self.level = (self.level + ramp_step(self.tone.attack, rate)).min(1.0);
+        } else {
+            self.level = (self.level - ramp_step(self.tone.release, rate)).max(0.0);
+        }
+
+        // Every beep starts at the same point of the wave
+        if self.level == 0.0 {
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let wave = match self.tone.waveform {
+            Waveform::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (self.phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+        };
+        self.phase = (self.phase + self.tone.frequency / rate).fract();
+
+        wave * self.level * self.tone.volume
+    }
+
+    pub fn fill(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.next_sample();
+        }
+    }
+}
+
+impl Audio for SampleGenerator {
+    fn set_sound_on(&mut self, on: bool) {
+        self.sound_on = on;
+    }
+}
+
+/// Writes the beep to a 16-bit WAV file. Every `set_sound_on` call adds one
+/// frame of samples, exactly what a real-time backend would have played
+pub struct WavAudio<W: Write + Seek> {
+    generator: SampleGenerator,
+    writer: hound::WavWriter<W>,
+    frames: u64,
+    buffer: Vec<f32>,
+    error: Option<hound::Error>,
+}
+
+impl WavAudio<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        tone: Tone,
+        sample_rate: u32,
+    ) -> Result<Self, hound::Error> {
+        Self::new(BufWriter::new(File::create(path)?), tone, sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavAudio<W> {
+    pub fn new(writer: W, tone: Tone, sample_rate: u32) -> Result<Self, hound::Error> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        Ok(Self {
+            generator: SampleGenerator::new(tone, sample_rate),
+            writer: hound::WavWriter::new(writer, spec)?,
+            frames: 0,
+            buffer: Vec::new(),
+            error: None,
+        })
+    }
+
+    /// Finalizes the file, or returns the first error that happened while
+    /// writing samples
+    pub fn finish(self) -> Result<(), hound::Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.writer.finalize()
+    }
+}
+
+impl<W: Write + Seek> Audio for WavAudio<W> {
+    fn set_sound_on(&mut self, on: bool) {
+        // Sample rates that aren't a multiple of the frame rate get frames of
+        // slightly different lengths, but never drift
+        let rate = self.generator.sample_rate() as u64;
+        let frame_rate = FRAME_RATE as u64;
+        let start = self.frames * rate / frame_rate;
+        let end = (self.frames + 1) * rate / frame_rate;
+        self.frames += 1;
+
+        self.buffer.resize((end - start) as usize, 0.0);
+        self.generator.set_sound_on(on);
+        self.generator.fill(&mut self.buffer);
+
+        if self.error.is_some() {
+            return;
+        }
+        for &sample in &self.buffer {
+            if let Err(error) = self.writer.write_sample(to_i16(sample)) {
+                self.error = Some(error);
+                return;
+            }
+        }
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn ramp_step(seconds: f32, sample_rate: f32) -> f32 {
+    if seconds > 0.0 {
+        1.0 / (seconds * sample_rate)
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8;
+    use std::io::Cursor;
+
+    fn tone(waveform: Waveform) -> Tone {
+        Tone {
+            waveform,
+            frequency: 1.0,
+            volume: 1.0,
+            attack: 0.0,
+            release: 0.0,
+        }
+    }
+
+    fn generate(tone: Tone, sample_rate: u32, count: usize) -> Vec<f32> {
+        let mut generator = SampleGenerator::new(tone, sample_rate);
+        generator.set_sound_on(true);
+        let mut samples = vec![0.0; count];
+        generator.fill(&mut samples);
+        samples
+    }
+
+    #[test]
+    fn silent_when_off() {
+        let mut generator = SampleGenerator::new(Tone::default(), 44100);
+        let mut samples = [1.0; 64];
+        generator.fill(&mut samples);
+
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn waveforms() {
+        let square = generate(tone(Waveform::Square), 4, 4);
+        assert_eq!(square, [1.0, 1.0, -1.0, -1.0]);
+
+        let triangle = generate(tone(Waveform::Triangle), 4, 4);
+        assert_eq!(triangle, [-1.0, 0.0, 1.0, 0.0]);
+
+        let sine = generate(tone(Waveform::Sine), 4, 4);
+        let expected = [0.0, 1.0, 0.0, -1.0];
+        for (actual, expected) in sine.iter().zip(&expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn volume() {
+        let mut tone = tone(Waveform::Square);
+        tone.volume = 0.25;
+
+        assert_eq!(generate(tone, 4, 4), [0.25, 0.25, -0.25, -0.25]);
+    }
+
+    #[test]
+    fn attack_and_release() {
+        let mut tone = tone(Waveform::Square);
+        tone.frequency = 0.0;
+        tone.attack = 1.0;
+        tone.release = 0.5;
+
+        let mut generator = SampleGenerator::new(tone, 4);
+        generator.set_sound_on(true);
+        let mut attack = [0.0; 5];
+        generator.fill(&mut attack);
+        assert_eq!(attack, [0.25, 0.5, 0.75, 1.0, 1.0]);
+
+        generator.set_sound_on(false);
+        let mut release = [0.0; 3];
+        generator.fill(&mut release);
+        assert_eq!(release, [0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn wav_matches_sound_timer() {
+        // V0 = 4, ST = V0, loop forever
+        let program = [0x60, 0x04, 0xf0, 0x18, 0x12, 0x04];
+        let mut chip8 = Chip8::new();
+        chip8.set_program(&program);
+
+        let mut buffer = Cursor::new(Vec::new());
+        let mut audio = WavAudio::new(&mut buffer, Tone::default(), 6000).unwrap();
+        for _ in 0..6 {
+            chip8.run_frame(8);
+            audio.set_sound_on(chip8.sound_timer() > 0);
+        }
+        audio.finish().unwrap();
+
+        // The timer is set and ticked in the first frame, so it beeps for three
+        let mut generator = SampleGenerator::new(Tone::default(), 6000);
+        let mut expected = vec![0.0; 6 * 100];
+        for (frame, samples) in expected.chunks_mut(100).enumerate() {
+            generator.set_sound_on(frame < 3);
+            generator.fill(samples);
+        }
+        let expected: Vec<i16> = expected.into_iter().map(to_i16).collect();
+
+        buffer.set_position(0);
+        let actual: Vec<i16> = hound::WavReader::new(buffer)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, expected);
+        assert!(actual[..300].iter().any(|&sample| sample != 0));
+        assert!(actual[400..].iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn uneven_frame_lengths() {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut audio = WavAudio::new(&mut buffer, Tone::default(), 22050).unwrap();
+        for _ in 0..60 {
+            audio.set_sound_on(false);
+        }
+        audio.finish().unwrap();
+
+        buffer.set_position(0);
+        let reader = hound::WavReader::new(buffer).unwrap();
+        assert_eq!(reader.len(), 22050);
+    }
+}
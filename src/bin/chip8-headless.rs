@@ -0,0 +1,216 @@
+use chip8::{
+    save_png, Audio, Backend, Chip8, ControlFlowGraph, Font, Movie, Profiler, RomDatabase, Tone,
+    VideoRecorder, WavAudio, CYCLES_PER_FRAME, HEIGHT, WIDTH,
+};
+
+const DEFAULT_FRAMES: usize = 600;
+const SCREENSHOT_SCALE: usize = 8;
+const VIDEO_SCALE: usize = 4;
+const SAMPLE_RATE: u32 = 44100;
+
+struct Options {
+    rom: String,
+    frames: Option<usize>,
+    replay: Option<String>,
+    font: Option<String>,
+    seed: Option<u64>,
+    backend: Backend,
+    screenshot: Option<String>,
+    record_video: Option<String>,
+    wav_out: Option<String>,
+    profile: Option<String>,
+    cfg: Option<String>,
+    database: Option<String>,
+}
+
+fn main() {
+    let options = parse_args();
+
+    let replay = options
+        .replay
+        .as_ref()
+        .map(|path| Movie::load(path).expect("Failed to load movie"));
+
+    // Looked up the same way as in the GUI, so a ROM runs the same in both
+    let mut database = RomDatabase::embedded();
+    if let Some(path) = &options.database {
+        database.merge(RomDatabase::load(path).expect("Failed to load ROM database"));
+    }
+    let program = std::fs::read(&options.rom).expect("Failed to read ROM");
+    let info = database.lookup_program(&program);
+
+    // A movie's quirks win over the database's
+    let mut builder = match (&replay, info) {
+        (Some(movie), _) => movie.chip8_builder(),
+        (None, Some(info)) => Chip8::builder().rom_info(info),
+        (None, None) => Chip8::builder(),
+    };
+    match (options.seed, &replay) {
+        (Some(_), Some(_)) => eprintln!("Warning: --seed is ignored, the movie has its own"),
+        (Some(seed), None) => builder = builder.seed(seed),
+        (None, _) => {}
+    }
+    match (&options.font, &replay) {
+        (Some(_), Some(_)) => eprintln!("Warning: --font is ignored, the movie has its own"),
+        (Some(font), None) => builder = builder.font(load_font(font)),
+        (None, _) => {}
+    }
+    let mut chip8 = builder.backend(options.backend).build();
+    chip8.set_program(&program);
+
+    if let Some(movie) = &replay {
+        if movie.rom_hash != chip8.rom_hash() {
+            eprintln!("Warning: movie was recorded with a different ROM");
+        }
+    }
+
+    let cycles_per_frame = match (&replay, info) {
+        (Some(movie), _) => movie.cycles_per_frame,
+        (None, Some(info)) => info.cycles_per_frame(),
+        (None, None) => CYCLES_PER_FRAME,
+    };
+    let palette = info.and_then(|info| info.palette).unwrap_or_default();
+    let frames = options.frames.unwrap_or_else(|| {
+        replay
+            .as_ref()
+            .map_or(DEFAULT_FRAMES, |movie| movie.frames.len())
+    });
+
+    let mut video = options.record_video.as_ref().map(|path| {
+        VideoRecorder::create(path, WIDTH, HEIGHT, &palette, VIDEO_SCALE, Tone::default())
+            .expect("Failed to start video recording")
+    });
+
+    let mut wav_out = options.wav_out.as_ref().map(|path| {
+        WavAudio::create(path, Tone::default(), SAMPLE_RATE).expect("Failed to create WAV file")
+    });
+
+    if let Some(path) = &options.cfg {
+        let cfg = ControlFlowGraph::analyze(chip8.program(), chip8.memory_map().entry_point);
+        std::fs::write(path, cfg.to_dot()).expect("Failed to write control flow graph");
+    }
+
+    let profiler = options
+        .profile
+        .as_ref()
+        .map(|_| Profiler::attach(&mut chip8));
+
+    for frame in 0..frames {
+        let keys = replay
+            .as_ref()
+            .and_then(|movie| movie.frames.get(frame).copied())
+            .unwrap_or(0);
+
+        chip8.set_key_mask(keys);
+        chip8.run_frame(cycles_per_frame);
+        if let Some(fault) = chip8.fault() {
+            eprintln!("Stopped at frame {}: {}", frame, fault);
+            break;
+        }
+
+        let sound_on = chip8.sound_timer() > 0;
+        if let Some(wav) = &mut wav_out {
+            wav.set_sound_on(sound_on);
+        }
+        if let Some(recorder) = &mut video {
+            recorder
+                .record_frame(chip8.get_framebuffer(), sound_on)
+                .expect("Failed to record video frame");
+        }
+    }
+
+    if let (Some(path), Some(profiler)) = (&options.profile, profiler) {
+        let profile = profiler.detach(&mut chip8);
+        let entry_point = chip8.memory_map().entry_point;
+        let text = profile.report() + "\n" + &profile.heatmap(chip8.program(), entry_point);
+        std::fs::write(path, text).expect("Failed to write profile");
+    }
+
+    if let Some(recorder) = video {
+        recorder.finish().expect("Failed to finish video recording");
+    }
+    if let Some(wav) = wav_out {
+        wav.finish().expect("Failed to finish WAV file");
+    }
+
+    for row in chip8.get_framebuffer().chunks(WIDTH) {
+        let line: String = row
+            .iter()
+            .map(|&pixel| if pixel > 0 { '#' } else { '.' })
+            .collect();
+        println!("{}", line);
+    }
+
+    if let Some(path) = &options.screenshot {
+        let pixels = chip8.get_framebuffer();
+        save_png(path, pixels, WIDTH, HEIGHT, &palette, SCREENSHOT_SCALE)
+            .expect("Failed to save screenshot");
+    }
+
+    println!("rom: {}", chip8.rom_hash());
+    println!("seed: {}", chip8.seed());
+    println!("frames: {}", frames);
+    if chip8.fault().is_some() {
+        std::process::exit(1);
+    }
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        rom: "roms/pong.ch8".to_string(),
+        frames: None,
+        replay: None,
+        font: None,
+        seed: None,
+        backend: Backend::Interpreter,
+        screenshot: None,
+        record_video: None,
+        wav_out: None,
+        profile: None,
+        cfg: None,
+        database: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let frames = args.next().expect("--frames requires a number");
+                options.frames = Some(frames.parse().expect("Invalid frame count"));
+            }
+            "--replay" => options.replay = Some(args.next().expect("--replay requires a file")),
+            "--font" => options.font = Some(args.next().expect("--font requires a name or file")),
+            "--seed" => {
+                let seed = args.next().expect("--seed requires a number");
+                options.seed = Some(seed.parse().expect("Invalid seed"));
+            }
+            "--backend" => {
+                options.backend = match args.next().as_deref() {
+                    Some("interpreter") => Backend::Interpreter,
+                    Some("recompiler") => Backend::Recompiler,
+                    _ => panic!("--backend must be interpreter or recompiler"),
+                }
+            }
+            "--screenshot" => {
+                options.screenshot = Some(args.next().expect("--screenshot requires a file"))
+            }
+            "--record-video" => {
+                options.record_video = Some(args.next().expect("--record-video requires a file"))
+            }
+            "--wav-out" => options.wav_out = Some(args.next().expect("--wav-out requires a file")),
+            "--profile" => options.profile = Some(args.next().expect("--profile requires a file")),
+            "--cfg" => options.cfg = Some(args.next().expect("--cfg requires a file")),
+            "--database" => {
+                options.database = Some(args.next().expect("--database requires a file"))
+            }
+            _ => options.rom = arg,
+        }
+    }
+
+    options
+}
+
+/// A preset name or a font file
+fn load_font(name: &str) -> Font {
+    Font::preset(name).unwrap_or_else(|| Font::load(name).expect("Failed to load font"))
+}
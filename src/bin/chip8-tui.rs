@@ -0,0 +1,185 @@
+use chip8::{render_braille, render_half_blocks, Chip8, Palette, CYCLES_PER_FRAME, HEIGHT, WIDTH};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    io::{self, Write},
+    panic, thread,
+    time::{Duration, Instant},
+};
+
+const FRAME_DURATION: Duration = Duration::from_micros(16667);
+// Terminals only report key presses, so a key stays down for a few frames
+// after each press. Auto-repeat keeps it down while the key is held
+const KEY_HOLD_FRAMES: u8 = 8;
+
+struct Options {
+    rom: String,
+    braille: bool,
+    palette: Palette,
+}
+
+fn main() -> io::Result<()> {
+    let options = parse_args();
+
+    let mut chip8 = Chip8::new();
+    chip8.load_program(&options.rom);
+
+    let mut stdout = io::stdout();
+    let guard = TerminalGuard::enter(&mut stdout)?;
+    let result = run(&mut chip8, &options, &mut stdout);
+    drop(guard);
+
+    if let Some(fault) = chip8.fault() {
+        eprintln!("{}", fault);
+    }
+    result
+}
+
+/// Keeps the terminal in raw mode on the alternate screen, and puts it back
+/// when dropped, including when unwinding from a panic
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(stdout: &mut io::Stdout) -> io::Result<Self> {
+        // The panic message is printed before unwinding, so it would land
+        // on the alternate screen and vanish without restoring first
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn restore_terminal() {
+    let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+fn run(chip8: &mut Chip8, options: &Options, stdout: &mut io::Stdout) -> io::Result<()> {
+    let mut held = [0u8; 16];
+    let mut redraw = true;
+    let mut beeping = false;
+
+    loop {
+        let frame_start = Instant::now();
+
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(KeyEvent { code, modifiers }) => {
+                    let ctrl_c = code == KeyCode::Char('c') && modifiers == KeyModifiers::CONTROL;
+                    if code == KeyCode::Esc || ctrl_c {
+                        return Ok(());
+                    }
+                    if let Some(key) = map_keycode(code) {
+                        held[key as usize] = KEY_HOLD_FRAMES;
+                    }
+                }
+                Event::Resize(..) => redraw = true,
+                _ => {}
+            }
+        }
+
+        let mut keys = 0;
+        for (key, frames) in held.iter_mut().enumerate() {
+            if *frames > 0 {
+                keys |= 1 << key;
+                *frames -= 1;
+            }
+        }
+
+        chip8.set_key_mask(keys);
+        chip8.run_frame(CYCLES_PER_FRAME);
+        if chip8.fault().is_some() {
+            return Ok(());
+        }
+
+        if chip8.take_frame_if_dirty().is_some() || redraw {
+            let pixels = chip8.get_framebuffer();
+            let screen = if options.braille {
+                render_braille(pixels, WIDTH, HEIGHT, &options.palette)
+            } else {
+                render_half_blocks(pixels, WIDTH, HEIGHT, &options.palette)
+            };
+            queue!(stdout, cursor::MoveTo(0, 0), Print(screen))?;
+            redraw = false;
+        }
+
+        // Ring the bell once at the start of every beep
+        let sound = chip8.sound_timer() > 0;
+        if sound && !beeping {
+            queue!(stdout, Print('\x07'))?;
+        }
+        beeping = sound;
+        stdout.flush()?;
+
+        thread::sleep(FRAME_DURATION.saturating_sub(frame_start.elapsed()));
+    }
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        rom: "roms/pong.ch8".to_string(),
+        braille: false,
+        palette: Palette::default(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--braille" => options.braille = true,
+            "--palette" => {
+                let name = args.next().expect("--palette requires a name");
+                options.palette = Palette::preset(&name).expect("Unknown palette");
+            }
+            _ => options.rom = arg,
+        }
+    }
+
+    options
+}
+
+fn map_keycode(code: KeyCode) -> Option<u8> {
+    let key = match code {
+        KeyCode::Char(c) => c.to_ascii_lowercase(),
+        _ => return None,
+    };
+
+    match key {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
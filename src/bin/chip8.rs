@@ -1,80 +1,890 @@
-use chip8::Chip8;
+use chip8::{
+    save_png, Audio, Cheat, CheatKind, CheatSet, Chip8, Comparison, Font, Launcher, MemorySearch,
+    Movie, Osd, Palette, Persistence, PhosphorFilter, RamFill, RomDatabase, RomInfo,
+    SampleGenerator, ScaleFilter, Scaler, Tone, VideoError, VideoRecorder, WavAudio, Waveform,
+    CYCLES_PER_FRAME, HEIGHT, MAX_CYCLES_PER_FRAME, WIDTH,
+};
 use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
-use rodio::{source::SineWave, OutputStream, Sink};
-use std::time::{Duration, Instant};
+use rodio::{OutputStream, Sink, Source};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
-const PIXEL_OFF_COLOR: u32 = 0x00000000;
-const PIXEL_ON_COLOR: u32 = 0xffffffff;
+const FRAME_DURATION: Duration = Duration::from_micros(16667);
+const DEFAULT_DECAY: f32 = 0.3;
+// The scaler and the window scale multiply to the original 16x
+const SCALE_FACTOR: usize = 8;
+const SCREENSHOT_SCALE: usize = 8;
+const VIDEO_SCALE: usize = 4;
+const SAMPLE_RATE: u32 = 44100;
+const FAST_FORWARD_FRAMES: usize = 5;
+const OSD_SCALE: usize = 2;
+const MESSAGE_FRAMES: u32 = 120;
+// About twice a second
+const WATCH_INTERVAL: u32 = 30;
+const SEARCH_COMPARISONS: [(Comparison, &str); 4] = [
+    (Comparison::Increased, "increased"),
+    (Comparison::Decreased, "decreased"),
+    (Comparison::Changed, "changed"),
+    (Comparison::Unchanged, "unchanged"),
+];
+// Candidates listed in the OSD after a search step
+const SEARCH_SHOWN: usize = 4;
+
+struct Options {
+    rom: String,
+    record: Option<String>,
+    replay: Option<String>,
+    font: Option<String>,
+    persistence: Option<Persistence>,
+    /// Falls back to the database's colours, then the default palette
+    palette: Option<Palette>,
+    database: Option<String>,
+    filter: ScaleFilter,
+    record_video: Option<String>,
+    tone: Tone,
+    wav_out: Option<String>,
+    watch: bool,
+    keep_ram: bool,
+    cheats: String,
+}
 
 fn main() {
+    let options = parse_args();
+
+    let mut scaler = Scaler::new(options.filter, SCALE_FACTOR);
+    let (window_width, window_height) = scaler.output_size(WIDTH, HEIGHT);
     let mut window = Window::new(
         "Chip-8",
-        WIDTH,
-        HEIGHT,
+        window_width,
+        window_height,
         WindowOptions {
-            scale: Scale::X16,
+            scale: Scale::X2,
             ..WindowOptions::default()
         },
     )
     .unwrap();
-    let mut buffer = [0; WIDTH * HEIGHT];
+    window.limit_update_rate(Some(FRAME_DURATION));
+    let mut audio = RodioAudio::new(options.tone);
+
+    let mut database = RomDatabase::embedded();
+    if let Some(path) = &options.database {
+        database.merge(RomDatabase::load(path).expect("Failed to load ROM database"));
+    }
 
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let audio_sink = Sink::try_new(&stream_handle).unwrap();
-    let audio_source = SineWave::new(440);
-    audio_sink.append(audio_source);
-    audio_sink.pause();
+    if !Path::new(&options.rom).is_dir() {
+        run_game(
+            &mut window,
+            &mut scaler,
+            &mut audio,
+            &database,
+            &options,
+            &options.rom,
+            false,
+        );
+        return;
+    }
+
+    let mut launcher =
+        Launcher::scan(&options.rom, &database, OSD_SCALE).expect("Failed to read ROM directory");
+    let background = options.palette.unwrap_or_default().background();
+    while let Some(rom) = run_launcher(
+        &mut window,
+        (window_width, window_height),
+        &mut launcher,
+        background,
+    ) {
+        let rom = rom.to_string_lossy().into_owned();
+        let exit = run_game(
+            &mut window,
+            &mut scaler,
+            &mut audio,
+            &database,
+            &options,
+            &rom,
+            true,
+        );
+        if exit == Exit::Quit {
+            break;
+        }
+    }
+}
 
-    let mut chip8 = Chip8::new();
-    chip8.load_program("roms/pong.ch8");
+#[derive(Debug, PartialEq, Eq)]
+enum Exit {
+    Quit,
+    Launcher,
+}
 
-    let mut last_draw = Instant::now();
-    let mut last_run = Instant::now();
-    let mut last_key_update = Instant::now();
-    let mut last_timer = Instant::now();
+/// Shows the ROM list until one is picked, or returns None to quit
+fn run_launcher(
+    window: &mut Window,
+    (width, height): (usize, usize),
+    launcher: &mut Launcher,
+    background: u32,
+) -> Option<PathBuf> {
+    let mut buffer = vec![background; width * height];
+    let mut redraw = true;
+    window.set_title("Chip-8 - select a ROM");
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        let time = Instant::now();
-        let key_pressed = window
-            .get_keys_pressed(KeyRepeat::Yes)
-            .get(0)
-            .and_then(map_keycode);
-        if key_pressed.is_some() || time - last_key_update >= Duration::from_millis(200) {
-            chip8.set_key_pressed(key_pressed);
-            last_key_update = Instant::now();
+        if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+            launcher.select_next();
+            redraw = true;
+        }
+        if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+            launcher.select_previous();
+            redraw = true;
+        }
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            if let Some(entry) = launcher.selected() {
+                return Some(entry.path.clone());
+            }
         }
 
-        if time - last_timer >= Duration::from_micros(16667) {
-            chip8.tick_timers();
-            last_timer = Instant::now();
+        if redraw {
+            buffer.iter_mut().for_each(|pixel| *pixel = background);
+            launcher.draw(&mut buffer, width, height);
+            window.update_with_buffer(&buffer, width, height).unwrap();
+            redraw = false;
+        } else {
+            window.update();
         }
+    }
+    None
+}
+
+/// Runs a ROM until the window is closed, Escape is pressed or, when started
+/// from the launcher, Backspace goes back to it
+fn run_game(
+    window: &mut Window,
+    scaler: &mut Scaler,
+    audio: &mut RodioAudio,
+    database: &RomDatabase,
+    options: &Options,
+    rom: &str,
+    from_launcher: bool,
+) -> Exit {
+    let (window_width, window_height) = scaler.output_size(WIDTH, HEIGHT);
+    let mut buffer = [0; WIDTH * HEIGHT];
+    let mut osd = Osd::new(OSD_SCALE);
+    let mut osd_buffer = vec![0; window_width * window_height];
+    let mut osd_shown = false;
+
+    let mut wav_out = options.wav_out.as_ref().map(|path| {
+        WavAudio::create(path, options.tone, SAMPLE_RATE).expect("Failed to create WAV file")
+    });
+
+    let replay = options
+        .replay
+        .as_ref()
+        .map(|path| Movie::load(path).expect("Failed to load movie"));
+
+    let program = fs::read(rom).expect("Failed to read ROM");
+    let info = database.lookup_program(&program);
 
-        if Instant::now() - last_run > Duration::from_millis(2) {
-            chip8.run();
-            last_run = Instant::now();
+    let mut chip8 = build_chip8(options, &program, replay.as_ref(), info);
+    let mut cycles_per_frame = match (&replay, info) {
+        (Some(movie), _) => movie.cycles_per_frame,
+        (None, Some(info)) => info.cycles_per_frame(),
+        (None, None) => CYCLES_PER_FRAME,
+    };
+    if let Some(movie) = &replay {
+        if movie.rom_hash != chip8.rom_hash() {
+            eprintln!("Warning: movie was recorded with a different ROM");
         }
+    }
+
+    let mut recording = options
+        .record
+        .as_ref()
+        .map(|_| Movie::new(&chip8, cycles_per_frame));
+    let mut frame = 0;
+    let mut palette = options
+        .palette
+        .or_else(|| info.and_then(|info| info.palette))
+        .unwrap_or_default();
+    let mut redraw = true;
+    let mut paused = false;
+    let mut title = String::new();
+    let mut show_stats = false;
+    let mut stats_start = Instant::now();
+    let mut stats_frames = 0;
+    let mut stats_instructions = 0;
+    let mut video = options.record_video.as_ref().map(|path| {
+        start_video(path, &palette, options.tone).expect("Failed to start video recording")
+    });
+    let mut cheats_path = CheatSet::path(&options.cheats, &chip8.rom_hash());
+    let mut cheats = load_cheats(&cheats_path, chip8.rom_hash());
+    let mut cheats_changed = false;
+    let mut selected_cheat = 0;
+    let mut search: Option<MemorySearch> = None;
+    let mut comparison = 0;
+    let mut watcher = if options.watch {
+        Some(RomWatcher::new(rom))
+    } else {
+        None
+    };
+    // Primed with the current frame, so there's something to draw if the
+    // game is paused before it runs a frame
+    let mut phosphor = options.persistence.map(|mode| {
+        let mut filter = PhosphorFilter::new(mode, palette.foreground(), palette.background());
+        filter.process(chip8.get_framebuffer());
+        filter
+    });
+
+    if let Some(info) = info {
+        println!("{}", info.title);
+        if !info.keys.is_empty() {
+            println!("Keys: {}", key_hints(info));
+        }
+        osd.show_message(info.title.clone(), MESSAGE_FRAMES * 2);
+    }
+
+    let mut exit = Exit::Quit;
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if from_launcher && window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+            exit = Exit::Launcher;
+            break;
+        }
+
+        // Movies store a single speed and no resets, so neither can change
+        // while one is recorded or replayed
+        let movie_active = recording.is_some() || replay.is_some();
+
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            paused = !paused;
+        }
+        if window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            if movie_active {
+                osd.show_message("Can't reset during a movie", MESSAGE_FRAMES);
+            } else {
+                chip8.hard_reset(RamFill::Zero);
+                cheats.reset();
+                redraw = true;
+                osd.show_message("Reset", MESSAGE_FRAMES);
+            }
+        }
+        if let Some(program) = watcher.as_mut().and_then(RomWatcher::poll) {
+            if movie_active {
+                osd.show_message("ROM changed, not reloaded during a movie", MESSAGE_FRAMES);
+            } else {
+                if cheats_changed {
+                    save_cheats(options, &cheats, &cheats_path);
+                    cheats_changed = false;
+                }
+
+                // The new program may be a different ROM as far as the
+                // database and the cheats are concerned
+                let info = database.lookup_program(&program);
+                let ram = options.keep_ram.then(|| chip8.ram().to_vec());
+                chip8 = build_chip8(options, &program, None, info);
+                if let Some(ram) = ram {
+                    chip8.write_ram(&ram, 0);
+                    chip8.reset();
+                }
+                cycles_per_frame = info.map_or(CYCLES_PER_FRAME, RomInfo::cycles_per_frame);
+                palette = options
+                    .palette
+                    .or_else(|| info.and_then(|info| info.palette))
+                    .unwrap_or_default();
+                if let Some(filter) = &mut phosphor {
+                    filter.set_colors(palette.foreground(), palette.background());
+                }
+                cheats_path = CheatSet::path(&options.cheats, &chip8.rom_hash());
+                cheats = load_cheats(&cheats_path, chip8.rom_hash());
+                selected_cheat = 0;
 
-        if time - last_draw >= Duration::from_millis(10) {
-            for (i, b) in chip8.get_framebuffer().iter().enumerate() {
-                buffer[i] = if *b > 0 {
-                    PIXEL_ON_COLOR
-                } else {
-                    PIXEL_OFF_COLOR
+                redraw = true;
+                let message = match info {
+                    Some(info) => format!("ROM reloaded: {}", info.title),
+                    None => "ROM reloaded".to_string(),
                 };
+                osd.show_message(message, MESSAGE_FRAMES);
+            }
+        }
+        let faster = window.is_key_pressed(Key::Equal, KeyRepeat::Yes)
+            || window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes);
+        let slower = window.is_key_pressed(Key::Minus, KeyRepeat::Yes)
+            || window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes);
+        if (faster || slower) && movie_active {
+            osd.show_message("Can't change speed during a movie", MESSAGE_FRAMES);
+        } else if faster || slower {
+            cycles_per_frame = if faster {
+                (cycles_per_frame + 1).min(MAX_CYCLES_PER_FRAME)
+            } else {
+                (cycles_per_frame - 1).max(1)
+            };
+            let message = format!("Speed: {} instructions/frame", cycles_per_frame);
+            osd.show_message(message, MESSAGE_FRAMES);
+        }
+        let next_cheat = window.is_key_pressed(Key::F5, KeyRepeat::No);
+        if next_cheat && !cheats.is_empty() {
+            selected_cheat = (selected_cheat + 1) % cheats.len();
+        }
+        let toggle_cheat = window.is_key_pressed(Key::F6, KeyRepeat::No);
+        if toggle_cheat && movie_active {
+            osd.show_message("Can't use cheats during a movie", MESSAGE_FRAMES);
+        } else if toggle_cheat {
+            if let Some(enabled) = cheats.get(selected_cheat).map(|cheat| !cheat.enabled) {
+                cheats_changed = cheats.set_enabled(selected_cheat, enabled, &mut chip8);
+            }
+        }
+        if next_cheat || toggle_cheat {
+            osd.show_message(cheat_message(&cheats, selected_cheat), MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+            search = Some(MemorySearch::new(&chip8));
+            osd.show_message("Search started", MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+            comparison = (comparison + 1) % SEARCH_COMPARISONS.len();
+            let message = format!("Search for {} values", SEARCH_COMPARISONS[comparison].1);
+            osd.show_message(message, MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            let message = match &mut search {
+                Some(search) => {
+                    search.filter(&chip8, SEARCH_COMPARISONS[comparison].0);
+                    search_message(search)
+                }
+                None => "F7 starts a search".to_string(),
+            };
+            osd.show_message(message, MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            let message = match search.as_ref().map(MemorySearch::candidates) {
+                Some(&[address]) => {
+                    let cheat = Cheat {
+                        name: format!("Freeze {:03x}", address),
+                        kind: CheatKind::Freeze,
+                        address,
+                        bytes: vec![chip8.ram()[address as usize]],
+                        enabled: false,
+                    };
+                    selected_cheat = cheats.add(cheat).expect("A RAM byte is in bounds");
+                    cheats_changed = true;
+                    format!("Added cheat {}, F6 turns it on", selected_cheat + 1)
+                }
+                _ => "Narrow the search down to one address first".to_string(),
+            };
+            osd.show_message(message, MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F1, KeyRepeat::No) {
+            show_stats = !show_stats;
+            if !show_stats {
+                osd.set_stats(None);
+            }
+        }
+
+        let fast_forward = window.is_key_down(Key::Tab);
+        let frames = if paused {
+            window.is_key_pressed(Key::N, KeyRepeat::Yes) as usize
+        } else if fast_forward {
+            FAST_FORWARD_FRAMES
+        } else {
+            1
+        };
+
+        for _ in 0..frames {
+            let replayed_keys = replay
+                .as_ref()
+                .and_then(|movie| movie.frames.get(frame).copied());
+            let keys = replayed_keys.unwrap_or_else(|| key_mask(window));
+
+            if let Some(movie) = &mut recording {
+                movie.frames.push(keys);
             }
 
-            window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
-            last_draw = Instant::now();
+            chip8.set_key_mask(keys);
+            if !movie_active {
+                cheats.apply(&mut chip8);
+            }
+            if chip8.fault().is_some() {
+                break;
+            }
+            chip8.run_frame(cycles_per_frame);
+            frame += 1;
+            if let Some(fault) = chip8.fault() {
+                eprintln!("{}", fault);
+            }
+            if let Some(filter) = &mut phosphor {
+                filter.process(chip8.get_framebuffer());
+            }
+
+            let sound_on = chip8.sound_timer() > 0;
+            if let Some(wav) = &mut wav_out {
+                wav.set_sound_on(sound_on);
+            }
+            if let Some(recorder) = &mut video {
+                if let Err(error) = recorder.record_frame(chip8.get_framebuffer(), sound_on) {
+                    eprintln!("Failed to record video frame: {:?}", error);
+                    osd.show_message("Video recording failed", MESSAGE_FRAMES);
+                    video = None;
+                }
+            }
         }
+        osd.set_banner(match chip8.fault() {
+            Some(fault) => Some(format!("Halted: {} (F2 resets)", fault)),
+            None if paused => Some("Paused".to_string()),
+            None => None,
+        });
+        stats_frames += 1;
+        stats_instructions += frames * cycles_per_frame;
 
-        if chip8.sound_timer() > 0 {
-            audio_sink.play();
+        if window.is_key_pressed(Key::F3, KeyRepeat::No) {
+            palette = palette.next_preset();
+            redraw = true;
+            osd.show_message(format!("Palette: {}", palette.name), MESSAGE_FRAMES);
+            if let Some(filter) = &mut phosphor {
+                filter.set_colors(palette.foreground(), palette.background());
+            }
+        }
+        if window.is_key_pressed(Key::F4, KeyRepeat::No) {
+            scaler.set_filter(scaler.filter().next());
+            redraw = true;
+            let message = format!("Filter: {:?}", scaler.filter());
+            osd.show_message(message, MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            osd.show_message(save_screenshot(&chip8, &palette), MESSAGE_FRAMES);
+        }
+        if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            let message = match video.take() {
+                Some(recorder) => stop_video(recorder),
+                None => {
+                    let path = format!("chip8-{}.gif", timestamp());
+                    match start_video(&path, &palette, options.tone) {
+                        Ok(recorder) => {
+                            video = Some(recorder);
+                            format!("Recording {}", path)
+                        }
+                        Err(error) => {
+                            eprintln!("Failed to start video recording: {:?}", error);
+                            "Video recording failed".to_string()
+                        }
+                    }
+                }
+            };
+            osd.show_message(message, MESSAGE_FRAMES);
+        }
+
+        let elapsed = stats_start.elapsed();
+        if show_stats && elapsed >= Duration::from_secs(1) {
+            let seconds = elapsed.as_secs_f64();
+            let stats = format!(
+                "{:.0} FPS {:.0} IPS",
+                stats_frames as f64 / seconds,
+                stats_instructions as f64 / seconds
+            );
+            osd.set_stats(Some(stats));
+        }
+        if elapsed >= Duration::from_secs(1) {
+            stats_start = Instant::now();
+            stats_frames = 0;
+            stats_instructions = 0;
+        }
+
+        // The OSD changes without the screen changing, and leaves marks
+        // behind when it disappears
+        let osd_visible = osd.is_visible();
+        if osd_visible || osd_shown {
+            redraw = true;
+        }
+        osd_shown = osd_visible;
+
+        let new_title = window_title(cycles_per_frame, paused, fast_forward);
+        if new_title != title {
+            window.set_title(&new_title);
+            title = new_title;
+        }
+
+        // The filter only advances with emulated frames, so it stays put
+        // while paused
+        let colors = if let Some(filter) = &phosphor {
+            Some(filter.output())
+        } else if chip8.take_frame_if_dirty().is_some() || redraw {
+            palette.render_into(chip8.get_framebuffer(), &mut buffer);
+            Some(&buffer[..])
         } else {
-            audio_sink.pause();
+            None
+        };
+        redraw = false;
+
+        match colors {
+            Some(colors) => {
+                let mut output = scaler.scale(colors, WIDTH, HEIGHT);
+                if osd_visible {
+                    osd_buffer.copy_from_slice(output);
+                    osd.draw(&mut osd_buffer, window_width, window_height);
+                    output = &osd_buffer;
+                }
+                window
+                    .update_with_buffer(output, window_width, window_height)
+                    .unwrap();
+            }
+            None => window.update(),
         }
+
+        osd.tick();
+
+        // The sound timer doesn't run while paused, so neither does the beep
+        audio.set_sound_on(!paused && chip8.sound_timer() > 0);
+    }
+
+    audio.set_sound_on(false);
+    if cheats_changed {
+        save_cheats(options, &cheats, &cheats_path);
+    }
+    if let Some(recorder) = video {
+        stop_video(recorder);
+    }
+    if let Some(wav) = wav_out {
+        wav.finish().expect("Failed to finish WAV file");
     }
+    if let (Some(path), Some(movie)) = (&options.record, &recording) {
+        movie.save(path).expect("Failed to save movie");
+    }
+    exit
+}
+
+/// Plays the samples of a `SampleGenerator` through rodio. The generator runs
+/// on the audio thread and only the sound flag is shared with it
+struct RodioAudio {
+    sound_on: Arc<AtomicBool>,
+    _stream: OutputStream,
+    _sink: Sink,
+}
+
+impl RodioAudio {
+    fn new(tone: Tone) -> Self {
+        let (stream, stream_handle) = OutputStream::try_default().unwrap();
+        let sink = Sink::try_new(&stream_handle).unwrap();
+        let sound_on = Arc::new(AtomicBool::new(false));
+
+        sink.append(GeneratorSource {
+            generator: SampleGenerator::new(tone, SAMPLE_RATE),
+            sound_on: sound_on.clone(),
+        });
+
+        Self {
+            sound_on,
+            _stream: stream,
+            _sink: sink,
+        }
+    }
+}
+
+impl Audio for RodioAudio {
+    fn set_sound_on(&mut self, on: bool) {
+        self.sound_on.store(on, Ordering::Relaxed);
+    }
+}
+
+struct GeneratorSource {
+    generator: SampleGenerator,
+    sound_on: Arc<AtomicBool>,
+}
+
+impl Iterator for GeneratorSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.generator
+            .set_sound_on(self.sound_on.load(Ordering::Relaxed));
+        Some(self.generator.next_sample())
+    }
+}
+
+impl Source for GeneratorSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.generator.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Polls the modification time of the ROM file and rereads it when it changes
+struct RomWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    countdown: u32,
+}
+
+impl RomWatcher {
+    fn new(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let modified = modified_time(&path);
+        Self {
+            path,
+            modified,
+            countdown: WATCH_INTERVAL,
+        }
+    }
+
+    /// Call once per displayed frame, returns the new program when the file
+    /// has changed
+    fn poll(&mut self) -> Option<Vec<u8>> {
+        self.countdown -= 1;
+        if self.countdown > 0 {
+            return None;
+        }
+        self.countdown = WATCH_INTERVAL;
+
+        let modified = modified_time(&self.path)?;
+        if Some(modified) == self.modified {
+            return None;
+        }
+
+        // An assembler may still be writing the file, so an empty or
+        // unreadable ROM is tried again on the next poll
+        let program = fs::read(&self.path).ok().filter(|data| !data.is_empty())?;
+        self.modified = Some(modified);
+        Some(program)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        rom: "roms/pong.ch8".to_string(),
+        record: None,
+        replay: None,
+        font: None,
+        persistence: None,
+        palette: None,
+        database: None,
+        filter: ScaleFilter::default(),
+        record_video: None,
+        tone: Tone::default(),
+        wav_out: None,
+        watch: false,
+        keep_ram: false,
+        cheats: "cheats".to_string(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => options.record = Some(args.next().expect("--record requires a file")),
+            "--replay" => options.replay = Some(args.next().expect("--replay requires a file")),
+            "--font" => options.font = Some(args.next().expect("--font requires a name or file")),
+            "--persistence" => {
+                let mode = args.next().expect("--persistence requires a mode");
+                options.persistence = Some(parse_persistence(&mode));
+            }
+            "--palette" => {
+                let name = args.next().expect("--palette requires a name");
+                options.palette = Some(Palette::preset(&name).expect("Unknown palette"));
+            }
+            "--record-video" => {
+                options.record_video = Some(args.next().expect("--record-video requires a file"))
+            }
+            "--database" => {
+                options.database = Some(args.next().expect("--database requires a file"))
+            }
+            "--watch" => options.watch = true,
+            "--keep-ram" => options.keep_ram = true,
+            "--cheats" => options.cheats = args.next().expect("--cheats requires a directory"),
+            "--wav-out" => options.wav_out = Some(args.next().expect("--wav-out requires a file")),
+            "--waveform" => {
+                options.tone.waveform = match args.next().as_deref() {
+                    Some("square") => Waveform::Square,
+                    Some("sine") => Waveform::Sine,
+                    Some("triangle") => Waveform::Triangle,
+                    _ => panic!("--waveform must be square, sine or triangle"),
+                }
+            }
+            "--frequency" => {
+                let frequency = args.next().expect("--frequency requires a number");
+                options.tone.frequency = frequency.parse().expect("Invalid frequency");
+            }
+            "--volume" => {
+                let volume = args.next().expect("--volume requires a number");
+                options.tone.volume = volume.parse().expect("Invalid volume");
+            }
+            "--filter" => {
+                options.filter = match args.next().as_deref() {
+                    Some("nearest") => ScaleFilter::Nearest,
+                    Some("scanlines") => ScaleFilter::Scanlines,
+                    Some("grid") => ScaleFilter::Grid,
+                    Some("crt") => ScaleFilter::Crt,
+                    _ => panic!("--filter must be nearest, scanlines, grid or crt"),
+                }
+            }
+            _ => options.rom = arg,
+        }
+    }
+
+    options
+}
+
+fn parse_persistence(mode: &str) -> Persistence {
+    match mode {
+        "blend" => Persistence::Blend,
+        "decay" => Persistence::Decay(DEFAULT_DECAY),
+        _ => match mode.strip_prefix("decay=") {
+            Some(decay) => Persistence::Decay(decay.parse().expect("Invalid decay")),
+            None => panic!("--persistence must be blend, decay or decay=<0..1>"),
+        },
+    }
+}
+
+/// A movie's quirks and font win over the database and the options
+fn build_chip8(
+    options: &Options,
+    program: &[u8],
+    replay: Option<&Movie>,
+    info: Option<&RomInfo>,
+) -> Chip8 {
+    let mut builder = match (replay, info) {
+        (Some(movie), _) => movie.chip8_builder(),
+        (None, Some(info)) => Chip8::builder().rom_info(info),
+        (None, None) => Chip8::builder(),
+    };
+    match (&options.font, replay) {
+        (Some(_), Some(_)) => eprintln!("Warning: --font is ignored, the movie has its own"),
+        (Some(font), None) => builder = builder.font(load_font(font)),
+        (None, _) => {}
+    }
+
+    let mut chip8 = builder.build();
+    chip8.set_program(program);
+    chip8
+}
+
+/// An empty set when there's no cheat file for the ROM
+fn load_cheats(path: &Path, rom_hash: String) -> CheatSet {
+    if !path.exists() {
+        return CheatSet::new(rom_hash);
+    }
+    CheatSet::load(path).unwrap_or_else(|error| {
+        eprintln!("Failed to load cheats: {:?}", error);
+        CheatSet::new(rom_hash)
+    })
+}
+
+fn save_cheats(options: &Options, cheats: &CheatSet, path: &Path) {
+    let saved = fs::create_dir_all(&options.cheats)
+        .map_err(Into::into)
+        .and_then(|()| cheats.save(path));
+    if let Err(error) = saved {
+        eprintln!("Failed to save cheats: {:?}", error);
+    }
+}
+
+fn cheat_message(cheats: &CheatSet, selected: usize) -> String {
+    match cheats.get(selected) {
+        Some(cheat) => {
+            let state = if cheat.enabled { "on" } else { "off" };
+            format!("Cheat {}: {} ({})", selected + 1, cheat.name, state)
+        }
+        None => "No cheats for this ROM".to_string(),
+    }
+}
+
+fn search_message(search: &MemorySearch) -> String {
+    let candidates = search.candidates();
+    let shown: Vec<String> = candidates
+        .iter()
+        .take(SEARCH_SHOWN)
+        .map(|address| format!("{:03x}", address))
+        .collect();
+    let more = if candidates.len() > SEARCH_SHOWN {
+        " ..."
+    } else {
+        ""
+    };
+    format!("{} left: {}{}", candidates.len(), shown.join(" "), more)
+}
+
+/// Like "up 1, down 4", with the keys as hex digits
+fn key_hints(info: &RomInfo) -> String {
+    info.keys
+        .iter()
+        .map(|(action, key)| format!("{} {:X}", action, key))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn window_title(cycles_per_frame: usize, paused: bool, fast_forward: bool) -> String {
+    let mut title = format!("Chip-8 - {} instructions/frame", cycles_per_frame);
+    if paused {
+        title.push_str(" - paused");
+    } else if fast_forward {
+        title.push_str(" - fast forward");
+    }
+    title
+}
+
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+/// Returns a message for the OSD
+fn save_screenshot(chip8: &Chip8, palette: &Palette) -> String {
+    let path = format!("chip8-{}.png", timestamp());
+
+    let pixels = chip8.get_framebuffer();
+    match save_png(&path, pixels, WIDTH, HEIGHT, palette, SCREENSHOT_SCALE) {
+        Ok(()) => format!("Saved {}", path),
+        Err(error) => {
+            eprintln!("Failed to save screenshot: {:?}", error);
+            "Screenshot failed".to_string()
+        }
+    }
+}
+
+fn start_video(path: &str, palette: &Palette, tone: Tone) -> Result<VideoRecorder, VideoError> {
+    VideoRecorder::create(path, WIDTH, HEIGHT, palette, VIDEO_SCALE, tone)
+}
+
+/// Returns a message for the OSD
+fn stop_video(recorder: VideoRecorder) -> String {
+    match recorder.finish() {
+        Ok(()) => "Stopped video recording".to_string(),
+        Err(error) => {
+            eprintln!("Failed to finish video recording: {:?}", error);
+            "Video recording failed".to_string()
+        }
+    }
+}
+
+/// A preset name or a font file
+fn load_font(name: &str) -> Font {
+    Font::preset(name).unwrap_or_else(|| Font::load(name).expect("Failed to load font"))
+}
+
+fn key_mask(window: &Window) -> u16 {
+    window
+        .get_keys()
+        .iter()
+        .filter_map(map_keycode)
+        .fold(0, |mask, key| mask | 1 << key)
 }
 
 fn map_keycode(key: &Key) -> Option<u8> {
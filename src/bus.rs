@@ -1,9 +1,28 @@
-use crate::{framebuffer::Framebuffer, keyboard::Keyboard, ram::Ram};
+use std::{
+    cell::RefCell,
+    ops::{Range, RangeInclusive},
+};
+
+use crate::{
+    cache::InstructionCache,
+    framebuffer::{DirtyRect, Framebuffer},
+    hooks::{AccessKind, HookId, Hooks, MemoryAccess},
+    instruction::{DecodeError, Instruction},
+    keyboard::Keyboard,
+    ram::Ram,
+};
 
 pub struct Bus {
     ram: Ram,
     keyboard: Keyboard,
     framebuffer: Framebuffer,
+    cache: InstructionCache,
+    cache_enabled: bool,
+    code_generation: u64,
+    // Code overwritten since the recompiler last took it
+    code_writes: Option<Range<u16>>,
+    // None while there are no hooks, so plain accesses only pay for a check
+    hooks: Option<RefCell<Hooks>>,
 }
 
 impl Bus {
@@ -12,15 +31,129 @@ impl Bus {
             ram: Ram::new(),
             keyboard: Keyboard::new(),
             framebuffer: Framebuffer::new(),
+            cache: InstructionCache::new(),
+            cache_enabled: true,
+            code_generation: 0,
+            code_writes: None,
+            hooks: None,
         }
     }
 
+    /// Clears the screen, keys and cached code. RAM is left alone
+    pub fn reset(&mut self) {
+        self.keyboard.set_key_mask(0);
+        self.framebuffer.clear();
+        self.cache.clear();
+        self.code_generation += 1;
+    }
+
+    pub fn fill_ram<F: FnMut() -> u8>(&mut self, f: F) {
+        self.ram.fill_with(f);
+        self.cache.clear();
+        self.code_generation += 1;
+    }
+
     pub fn read_ram(&self, address: u16) -> u8 {
-        self.ram.read(address as usize).unwrap() // TODO: Handle error
+        let value = self.ram.read(address as usize).unwrap(); // TODO: Handle error
+        if let Some(hooks) = &self.hooks {
+            hooks
+                .borrow_mut()
+                .notify(MemoryAccess::new(AccessKind::Read, address, value as u16));
+        }
+        value
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        self.ram.as_slice()
     }
 
     pub fn write_ram(&mut self, data: &[u8], address: u16) {
         self.ram.write(data, address as usize).unwrap(); // TODO: Handle error
+        if self.cache.invalidate(address, data.len()) || !self.cache_enabled {
+            let end = address + data.len() as u16;
+            self.code_writes = Some(match self.code_writes.take() {
+                Some(writes) => writes.start.min(address)..writes.end.max(end),
+                None => address..end,
+            });
+        }
+
+        if let Some(hooks) = &mut self.hooks {
+            let hooks = hooks.get_mut();
+            for (offset, &byte) in data.iter().enumerate() {
+                let address = address.wrapping_add(offset as u16);
+                hooks.notify(MemoryAccess::new(AccessKind::Write, address, byte as u16));
+            }
+        }
+    }
+
+    pub fn fetch_instruction(&mut self, address: u16) -> Result<Instruction, DecodeError> {
+        if let Some(hooks) = &mut self.hooks {
+            let opcode = opcode(&self.ram, address);
+            hooks
+                .get_mut()
+                .notify(MemoryAccess::new(AccessKind::Fetch, address, opcode));
+        }
+
+        if let Some(instruction) = self.cache.get(address) {
+            return Ok(instruction);
+        }
+
+        let instruction = Instruction::decode(opcode(&self.ram, address))?;
+
+        if self.cache_enabled {
+            self.cache.insert(address, instruction);
+        }
+
+        Ok(instruction)
+    }
+
+    /// The decoded instruction, if it's cached. Skips the fetch hooks
+    #[inline]
+    pub fn cached_instruction(&self, address: u16) -> Option<Instruction> {
+        self.cache.get(address)
+    }
+
+    pub fn add_hook(
+        &mut self,
+        kind: AccessKind,
+        range: RangeInclusive<u16>,
+        callback: Box<dyn FnMut(MemoryAccess)>,
+    ) -> HookId {
+        let hooks = self.hooks.get_or_insert_with(Default::default);
+        hooks.get_mut().add(kind, range, callback)
+    }
+
+    pub fn remove_hook(&mut self, id: HookId) -> bool {
+        let hooks = match &mut self.hooks {
+            Some(hooks) => hooks.get_mut(),
+            None => return false,
+        };
+        let removed = hooks.remove(id);
+        if hooks.is_empty() {
+            self.hooks = None;
+        }
+        removed
+    }
+
+    pub fn has_hooks(&self) -> bool {
+        self.hooks.is_some()
+    }
+
+    pub fn set_instruction_cache_enabled(&mut self, enabled: bool) {
+        self.cache_enabled = enabled;
+        self.cache.clear();
+        self.code_generation += 1;
+    }
+
+    /// Bumped when all code may have changed, like on a reset
+    pub fn code_generation(&self) -> u64 {
+        self.code_generation
+    }
+
+    /// The range covering every write to cached code since the last call,
+    /// or to any RAM while the cache is off
+    pub fn take_code_writes(&mut self) -> Option<Range<u16>> {
+        self.code_writes.take()
     }
 
     pub fn clear_screen(&mut self) {
@@ -39,6 +172,14 @@ impl Bus {
         self.keyboard.set_key_pressed(key)
     }
 
+    pub fn set_key_mask(&mut self, keys: u16) {
+        self.keyboard.set_key_mask(keys)
+    }
+
+    pub fn key_mask(&self) -> u16 {
+        self.keyboard.key_mask()
+    }
+
     pub fn draw(&mut self, x: u8, y: u8, byte: u8) -> bool {
         self.framebuffer.draw(x, y, byte)
     }
@@ -46,4 +187,19 @@ impl Bus {
     pub fn get_framebuffer(&self) -> &[u8] {
         self.framebuffer.get_buffer()
     }
+
+    pub fn is_framebuffer_dirty(&self) -> bool {
+        self.framebuffer.is_dirty()
+    }
+
+    pub fn take_dirty(&mut self) -> Option<DirtyRect> {
+        self.framebuffer.take_dirty()
+    }
+}
+
+/// Fetches aren't data reads, so this skips the read hooks
+fn opcode(ram: &Ram, address: u16) -> u16 {
+    let hi = ram.read(address as usize).unwrap() as u16;
+    let lo = ram.read(address.wrapping_add(1) as usize).unwrap() as u16;
+    hi << 8 | lo
 }
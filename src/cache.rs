@@ -0,0 +1,78 @@
+use crate::{instruction::Instruction, ram::RAM_SIZE};
+
+pub struct InstructionCache {
+    entries: [Option<Instruction>; RAM_SIZE],
+}
+
+impl InstructionCache {
+    pub fn new() -> Self {
+        Self {
+            entries: [None; RAM_SIZE],
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, address: u16) -> Option<Instruction> {
+        self.entries.get(address as usize).copied().flatten()
+    }
+
+    pub fn insert(&mut self, address: u16, instruction: Instruction) {
+        if let Some(entry) = self.entries.get_mut(address as usize) {
+            *entry = Some(instruction);
+        }
+    }
+
+    pub fn invalidate(&mut self, address: u16, len: usize) -> bool {
+        // An instruction starting one byte before the write overlaps it too
+        let start = (address as usize).saturating_sub(1).min(RAM_SIZE);
+        let end = (address as usize + len).min(RAM_SIZE);
+
+        let mut invalidated = false;
+        for entry in &mut self.entries[start..end] {
+            invalidated |= entry.take().is_some();
+        }
+
+        invalidated
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [None; RAM_SIZE];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get() {
+        let mut cache = InstructionCache::new();
+        assert_eq!(cache.get(0x200), None);
+
+        cache.insert(0x200, Instruction::Cls);
+        assert_eq!(cache.get(0x200), Some(Instruction::Cls));
+    }
+
+    #[test]
+    fn invalidate_overlapping() {
+        let mut cache = InstructionCache::new();
+        cache.insert(0x200, Instruction::Cls);
+        cache.insert(0x202, Instruction::Ret);
+        cache.insert(0x204, Instruction::Jp(0x200));
+
+        assert!(cache.invalidate(0x203, 1));
+        assert!(!cache.invalidate(0x203, 1));
+        assert_eq!(cache.get(0x200), Some(Instruction::Cls));
+        assert_eq!(cache.get(0x202), None);
+        assert_eq!(cache.get(0x204), Some(Instruction::Jp(0x200)));
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut cache = InstructionCache::new();
+        cache.insert(RAM_SIZE as u16, Instruction::Cls);
+        cache.invalidate(RAM_SIZE as u16, 2);
+
+        assert_eq!(cache.get(RAM_SIZE as u16), None);
+    }
+}
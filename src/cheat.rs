@@ -0,0 +1,461 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ram::RAM_SIZE,
+    text_format::{parse_hex, to_hex, LineError, LineReader},
+    Chip8,
+};
+
+const MAGIC: &str = "chip8-cheats 1";
+
+#[derive(Debug)]
+pub enum CheatError {
+    Io(io::Error),
+    Parse(usize),
+    UnexpectedEof,
+    /// The cheat's bytes don't fit in RAM at its address
+    OutOfBounds(u16),
+}
+
+impl From<io::Error> for CheatError {
+    fn from(error: io::Error) -> Self {
+        CheatError::Io(error)
+    }
+}
+
+impl From<LineError> for CheatError {
+    fn from(error: LineError) -> Self {
+        match error {
+            LineError::Io(error) => CheatError::Io(error),
+            LineError::UnexpectedEof => CheatError::UnexpectedEof,
+            LineError::Parse(line) => CheatError::Parse(line),
+        }
+    }
+}
+
+/// How a RAM byte compares to the previous snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal(u8),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+/// Narrows down the addresses of a value, like a score or lives counter, by
+/// comparing RAM snapshots taken as the game runs
+pub struct MemorySearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl MemorySearch {
+    /// Starts with every address as a candidate
+    pub fn new(chip8: &Chip8) -> Self {
+        Self {
+            snapshot: chip8.ram().to_vec(),
+            candidates: (0..RAM_SIZE as u16).collect(),
+        }
+    }
+
+    /// Keeps the candidates whose current value matches and takes a new
+    /// snapshot. Returns how many are left
+    pub fn filter(&mut self, chip8: &Chip8, comparison: Comparison) -> usize {
+        let ram = chip8.ram();
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|&address| {
+            let (old, new) = (snapshot[address as usize], ram[address as usize]);
+            match comparison {
+                Comparison::Equal(value) => new == value,
+                Comparison::Changed => new != old,
+                Comparison::Unchanged => new == old,
+                Comparison::Increased => new > old,
+                Comparison::Decreased => new < old,
+            }
+        });
+        self.snapshot = ram.to_vec();
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatKind {
+    /// Rewritten before every frame
+    Freeze,
+    /// Written once, and again after a reset. The program's bytes come back
+    /// when disabled
+    Patch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub kind: CheatKind,
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub enabled: bool,
+}
+
+/// The cheats for one ROM, stored as a text file named after its hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheatSet {
+    pub rom_hash: String,
+    cheats: Vec<Cheat>,
+    // Which patches are in RAM, by index into `cheats`
+    applied: Vec<bool>,
+}
+
+impl Cheat {
+    fn in_bounds(&self) -> bool {
+        !self.bytes.is_empty() && self.address as usize + self.bytes.len() <= RAM_SIZE
+    }
+}
+
+impl CheatSet {
+    pub fn new(rom_hash: String) -> Self {
+        Self {
+            rom_hash,
+            cheats: Vec::new(),
+            applied: Vec::new(),
+        }
+    }
+
+    /// Returns the new cheat's index
+    pub fn add(&mut self, cheat: Cheat) -> Result<usize, CheatError> {
+        if !cheat.in_bounds() {
+            return Err(CheatError::OutOfBounds(cheat.address));
+        }
+        self.cheats.push(cheat);
+        self.applied.push(false);
+        Ok(self.cheats.len() - 1)
+    }
+
+    /// Leaves RAM as it is, disable a patch first to undo it
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        if index >= self.cheats.len() {
+            return None;
+        }
+        self.applied.remove(index);
+        Some(self.cheats.remove(index))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Cheat> {
+        self.cheats.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    /// Where the cheats for a ROM live in a cheat directory
+    pub fn path<P: AsRef<Path>>(dir: P, rom_hash: &str) -> PathBuf {
+        dir.as_ref().join(format!("{}.cheats", rom_hash))
+    }
+
+    /// Rewrites frozen bytes where they differ, so unchanged code stays in
+    /// the instruction cache, and writes patches that aren't applied yet.
+    /// Call before every frame
+    pub fn apply(&mut self, chip8: &mut Chip8) {
+        for (cheat, applied) in self.cheats.iter().zip(&mut self.applied) {
+            if !cheat.enabled {
+                continue;
+            }
+            let start = cheat.address as usize;
+            let differs = chip8.ram()[start..start + cheat.bytes.len()] != cheat.bytes[..];
+            let write = match cheat.kind {
+                CheatKind::Freeze => differs,
+                CheatKind::Patch => !*applied,
+            };
+            if write {
+                chip8.write_ram(&cheat.bytes, cheat.address);
+            }
+            *applied = true;
+        }
+    }
+
+    /// Call after resetting the machine, which undoes the patches
+    pub fn reset(&mut self) {
+        self.applied.fill(false);
+    }
+
+    /// Disabling a patch writes the program's own bytes back. Returns false
+    /// if there's no cheat at `index`
+    pub fn set_enabled(&mut self, index: usize, enabled: bool, chip8: &mut Chip8) -> bool {
+        let cheat = match self.cheats.get_mut(index) {
+            Some(cheat) => cheat,
+            None => return false,
+        };
+        cheat.enabled = enabled;
+        self.applied[index] = false;
+        if enabled || cheat.kind != CheatKind::Patch {
+            return true;
+        }
+
+        let offset = (cheat.address as usize).checked_sub(chip8.memory_map().entry_point as usize);
+        let original = offset.and_then(|offset| {
+            chip8
+                .program()
+                .get(offset..offset + cheat.bytes.len())
+                .map(<[u8]>::to_vec)
+        });
+        if let Some(original) = original {
+            chip8.write_ram(&original, cheat.address);
+        }
+        true
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CheatError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CheatError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// One cheat per line: "<freeze|patch> <0|1> <address> <bytes> <name>",
+    /// with the address and bytes in hex
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), CheatError> {
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(writer, "rom {}", self.rom_hash)?;
+        for cheat in &self.cheats {
+            let kind = match cheat.kind {
+                CheatKind::Freeze => "freeze",
+                CheatKind::Patch => "patch",
+            };
+            writeln!(
+                writer,
+                "{} {} {:03x} {} {}",
+                kind,
+                cheat.enabled as u8,
+                cheat.address,
+                to_hex(&cheat.bytes),
+                cheat.name
+            )?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, CheatError> {
+        let mut lines = LineReader::new(reader);
+        lines.expect(MAGIC)?;
+        let (_, rom_hash) = lines.expect("rom")?;
+
+        let mut cheats = Self::new(rom_hash);
+        for line in lines {
+            let (number, line) = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let cheat = Self::parse_cheat(&line).ok_or(CheatError::Parse(number))?;
+            cheats.add(cheat)?;
+        }
+
+        Ok(cheats)
+    }
+
+    fn parse_cheat(line: &str) -> Option<Cheat> {
+        let mut parts = line.splitn(5, ' ');
+        let kind = match parts.next()? {
+            "freeze" => CheatKind::Freeze,
+            "patch" => CheatKind::Patch,
+            _ => return None,
+        };
+        let enabled = match parts.next()? {
+            "0" => false,
+            "1" => true,
+            _ => return None,
+        };
+        let address = u16::from_str_radix(parts.next()?, 16).ok()?;
+
+        let bytes = parse_hex(parts.next()?).filter(|bytes| !bytes.is_empty())?;
+
+        let name = parts.next().unwrap_or("").to_string();
+        Some(Cheat {
+            name,
+            kind,
+            address,
+            bytes,
+            enabled,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::COUNTER;
+
+    fn chip8() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.set_program(&COUNTER);
+        chip8
+    }
+
+    fn cheat(kind: CheatKind, address: u16, bytes: &[u8]) -> Cheat {
+        Cheat {
+            name: "Test".to_string(),
+            kind,
+            address,
+            bytes: bytes.to_vec(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn search_finds_counter() {
+        let mut chip8 = chip8();
+        let mut search = MemorySearch::new(&chip8);
+
+        chip8.run_cycles(6);
+        search.filter(&chip8, Comparison::Increased);
+        chip8.run_cycles(6);
+        search.filter(&chip8, Comparison::Increased);
+        search.filter(&chip8, Comparison::Unchanged);
+        let left = search.filter(&chip8, Comparison::Equal(2));
+
+        assert_eq!(left, 1);
+        assert_eq!(search.candidates(), [0x300]);
+    }
+
+    #[test]
+    fn freeze() {
+        let mut chip8 = chip8();
+        let mut cheats = CheatSet::new(chip8.rom_hash());
+        cheats
+            .add(cheat(CheatKind::Freeze, 0x300, &[0x42]))
+            .unwrap();
+
+        for _ in 0..3 {
+            cheats.apply(&mut chip8);
+            chip8.run_cycles(6);
+            assert_eq!(chip8.ram()[0x300], 0x43);
+        }
+    }
+
+    #[test]
+    fn disabled_patch_restores_program() {
+        let mut chip8 = chip8();
+        let mut cheats = CheatSet::new(chip8.rom_hash());
+        // Add 2 instead of 1
+        cheats
+            .add(cheat(CheatKind::Patch, 0x204, &[0x70, 0x02]))
+            .unwrap();
+
+        cheats.apply(&mut chip8);
+        chip8.run_cycles(6);
+        assert_eq!(chip8.ram()[0x300], 2);
+
+        cheats.set_enabled(0, false, &mut chip8);
+        cheats.apply(&mut chip8);
+        chip8.run_cycles(6);
+        assert_eq!(chip8.ram()[0x300], 3);
+    }
+
+    #[test]
+    fn write_read() {
+        let mut cheats = CheatSet::new("abc".to_string());
+        cheats.add(cheat(CheatKind::Freeze, 0x2f0, &[5])).unwrap();
+        let mut patch = cheat(CheatKind::Patch, 0x31a, &[0x12, 0x34]);
+        patch.name = "Skip the death check".to_string();
+        patch.enabled = false;
+        cheats.add(patch).unwrap();
+
+        let mut buffer = Vec::new();
+        cheats.write(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.ends_with("patch 0 31a 1234 Skip the death check\n"));
+
+        assert_eq!(CheatSet::read(buffer.as_slice()).unwrap(), cheats);
+    }
+
+    #[test]
+    fn parse_error() {
+        let text = "chip8-cheats 1\nrom abc\nfreeze x fff 01 Bad flag\n";
+        let result = CheatSet::read(text.as_bytes());
+
+        assert!(matches!(result, Err(CheatError::Parse(3))));
+    }
+
+    #[test]
+    fn truncated() {
+        let result = CheatSet::read("chip8-cheats 1\n".as_bytes());
+
+        assert!(matches!(result, Err(CheatError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let text = "chip8-cheats 1\nrom abc\nfreeze 1 fff 0102 Too far\n";
+        let result = CheatSet::read(text.as_bytes());
+        assert!(matches!(result, Err(CheatError::OutOfBounds(0xfff))));
+
+        let mut cheats = CheatSet::new("abc".to_string());
+        let result = cheats.add(cheat(CheatKind::Freeze, 0xfff, &[1, 2]));
+        assert!(matches!(result, Err(CheatError::OutOfBounds(0xfff))));
+        assert_eq!(
+            cheats.add(cheat(CheatKind::Freeze, 0xfff, &[1])).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn add_remove() {
+        let mut chip8 = chip8();
+        let mut cheats = CheatSet::new(chip8.rom_hash());
+        cheats.add(cheat(CheatKind::Freeze, 0x300, &[1])).unwrap();
+        cheats.add(cheat(CheatKind::Patch, 0x301, &[2])).unwrap();
+        cheats.apply(&mut chip8);
+
+        assert!(!cheats.set_enabled(2, false, &mut chip8));
+        assert_eq!(cheats.remove(2), None);
+        assert_eq!(cheats.remove(0).unwrap().address, 0x300);
+        assert_eq!(cheats.len(), 1);
+        assert_eq!(cheats.get(0).unwrap().address, 0x301);
+
+        // The patch kept its applied flag when the freeze before it went
+        chip8.write_ram(&[7], 0x301);
+        cheats.apply(&mut chip8);
+        assert_eq!(chip8.ram()[0x301], 7);
+        assert!(cheats.set_enabled(0, true, &mut chip8));
+        cheats.apply(&mut chip8);
+        assert_eq!(chip8.ram()[0x301], 2);
+    }
+
+    #[test]
+    fn patch_is_written_once() {
+        let mut chip8 = chip8();
+        let mut cheats = CheatSet::new(chip8.rom_hash());
+        // Start the counter at 0x10
+        cheats.add(cheat(CheatKind::Patch, 0x300, &[0x10])).unwrap();
+
+        cheats.apply(&mut chip8);
+        chip8.run_cycles(6);
+        cheats.apply(&mut chip8);
+        chip8.run_cycles(6);
+        assert_eq!(chip8.ram()[0x300], 0x12);
+
+        chip8.hard_reset(crate::RamFill::Zero);
+        cheats.reset();
+        cheats.apply(&mut chip8);
+        assert_eq!(chip8.ram()[0x300], 0x10);
+    }
+}
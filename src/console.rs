@@ -0,0 +1,120 @@
+use crate::Palette;
+use std::fmt::Write;
+
+const UPPER_HALF_BLOCK: char = '▀';
+const BRAILLE_BLANK: u32 = 0x2800;
+// Bit of the braille pattern for each dot, indexed by [row][column]
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Renders two pixels per character cell: the top one as the foreground of an
+/// upper half block, the bottom one as its background. Lines end with "\r\n"
+/// so the output also works in raw mode
+pub fn render_half_blocks(pixels: &[u8], width: usize, height: usize, palette: &Palette) -> String {
+    let mut output = String::new();
+    let mut current = None;
+
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            let top = palette.color(pixels[y * width + x]);
+            let bottom = if y + 1 < height {
+                palette.color(pixels[(y + 1) * width + x])
+            } else {
+                palette.background()
+            };
+
+            if current != Some((top, bottom)) {
+                set_colors(&mut output, top, bottom);
+                current = Some((top, bottom));
+            }
+            output.push(UPPER_HALF_BLOCK);
+        }
+        output.push_str("\x1b[0m\r\n");
+        current = None;
+    }
+
+    output
+}
+
+/// Renders 2x4 pixels per character cell with braille patterns, in the
+/// palette's foreground colour on its background
+pub fn render_braille(pixels: &[u8], width: usize, height: usize, palette: &Palette) -> String {
+    let mut output = String::new();
+
+    for y in (0..height).step_by(4) {
+        set_colors(&mut output, palette.foreground(), palette.background());
+        for x in (0..width).step_by(2) {
+            let mut pattern = BRAILLE_BLANK;
+            for (row, dots) in BRAILLE_DOTS.iter().enumerate() {
+                for (column, dot) in dots.iter().enumerate() {
+                    let (x, y) = (x + column, y + row);
+                    if x < width && y < height && pixels[y * width + x] > 0 {
+                        pattern |= dot;
+                    }
+                }
+            }
+            output.push(char::from_u32(pattern).unwrap());
+        }
+        output.push_str("\x1b[0m\r\n");
+    }
+
+    output
+}
+
+fn set_colors(output: &mut String, foreground: u32, background: u32) {
+    let [_, r, g, b] = foreground.to_be_bytes();
+    let [_, br, bg, bb] = background.to_be_bytes();
+    write!(
+        output,
+        "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+        r, g, b, br, bg, bb
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_colors(text: &str) -> String {
+        let mut output = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                output.push(c);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn half_blocks() {
+        #[rustfmt::skip]
+        let pixels = [
+            1, 0,
+            1, 1,
+            0, 1,
+        ];
+        let actual = render_half_blocks(&pixels, 2, 3, &Palette::CLASSIC);
+
+        assert_eq!(strip_colors(&actual), "▀▀\r\n▀▀\r\n");
+        assert!(actual.starts_with("\x1b[38;2;255;255;255;48;2;255;255;255m▀"));
+        assert!(actual.contains("\x1b[38;2;0;0;0;48;2;255;255;255m▀"));
+    }
+
+    #[test]
+    fn braille() {
+        #[rustfmt::skip]
+        let pixels = [
+            1, 0, 0, 0,
+            0, 1, 0, 0,
+            0, 0, 0, 0,
+            1, 1, 0, 1,
+            0, 0, 0, 0,
+        ];
+        let actual = render_braille(&pixels, 4, 5, &Palette::CLASSIC);
+
+        assert_eq!(strip_colors(&actual), "⣑⢀\r\n⠀⠀\r\n");
+    }
+}
@@ -1,6 +1,32 @@
-use rand::{prelude::ThreadRng, Rng};
+use std::fmt;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    bus::Bus,
+    instruction::{DecodeError, Instruction},
+    memory_map::{MemoryMap, ReservedWrites},
+    quirks::Quirks,
+    stack::Stack,
+};
+
+/// Why the CPU stopped. It stays on the faulting instruction until reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    ReservedWrite { address: u16, pc: u16 },
+}
 
-use crate::{bus::Bus, stack::Stack, ENTRY_POINT};
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::ReservedWrite { address, pc } => write!(
+                f,
+                "Write to reserved memory at {:#05x} from {:#05x}",
+                address, pc
+            ),
+        }
+    }
+}
 
 pub struct Cpu {
     vx: [u8; 16],
@@ -9,37 +35,82 @@ pub struct Cpu {
     sound_timer: u8,
     pc: u16,
     stack: Stack,
-    rng: ThreadRng,
-}
-
-#[derive(Debug)]
-struct InstructionData {
-    instruction: u16,
-    nnn: u16,
-    kk: u8,
-    x: u8,
-    y: u8,
-    n: u8,
+    rng: StdRng,
+    quirks: Quirks,
+    memory_map: MemoryMap,
+    fault: Option<Fault>,
 }
 
 impl Cpu {
-    pub fn new() -> Self {
+    pub fn new(quirks: Quirks, memory_map: MemoryMap, seed: u64) -> Self {
         Self {
             vx: [0; 16],
             i: 0,
             delay_timer: 0,
             sound_timer: 0,
-            pc: ENTRY_POINT as u16,
+            pc: memory_map.entry_point,
             stack: Stack::new(),
-            rng: rand::thread_rng(),
+            rng: StdRng::seed_from_u64(seed),
+            quirks,
+            memory_map,
+            fault: None,
         }
     }
 
+    /// Back to the power-on state, keeping the quirks and memory map
+    pub fn reset(&mut self, seed: u64) {
+        self.vx = [0; 16];
+        self.i = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.pc = self.memory_map.entry_point;
+        self.stack.clear();
+        self.rng = StdRng::seed_from_u64(seed);
+        self.fault = None;
+    }
+
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    #[inline(never)]
     pub fn run(&mut self, bus: &mut Bus) {
-        let instruction = self.fetch_instruction(bus);
+        let instruction = self.decode(bus);
+        self.pc = self.pc.wrapping_add(2);
+        self.execute(bus, instruction);
+    }
+
+    fn decode(&self, bus: &mut Bus) -> Instruction {
+        match bus.fetch_instruction(self.pc) {
+            Ok(instruction) => instruction,
+            Err(DecodeError::UnknownInstruction(word)) => {
+                panic!("Unknown instruction: {:x?} at {:x?}", word, self.pc)
+            }
+        }
+    }
+
+    /// Runs straight from the instruction cache, only going through the
+    /// bus for code that isn't cached yet. Fetch hooks aren't called
+    pub fn run_cycles(&mut self, bus: &mut Bus, cycles: usize) {
+        for _ in 0..cycles {
+            match bus.cached_instruction(self.pc) {
+                // Most ROMs spend their time in short wait loops
+                Some(Instruction::Jp(nnn)) => self.pc = nnn,
+                Some(instruction) => {
+                    self.pc = self.pc.wrapping_add(2);
+                    self.execute(bus, instruction);
+                }
+                None => self.run(bus),
+            }
+        }
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
 
-        let params = Self::parse_instruction(instruction);
-        self.execute(bus, params);
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
     }
 
     pub fn sound_timer(&self) -> u8 {
@@ -56,268 +127,234 @@ impl Cpu {
         }
     }
 
-    fn execute(&mut self, bus: &mut Bus, params: InstructionData) {
-        let f = (params.instruction & 0xf000) >> 12;
-        match f {
-            0x0 => match params.kk {
-                0xe0 => {
-                    // CLS
-                    bus.clear_screen();
-                }
-                0xee => {
-                    // RET
-                    let addr = self.stack.pop().unwrap(); // TODO: Handle error
-                    self.pc = addr;
-                }
-                _ => {
-                    panic!(
-                        "Unknown 0x0### instruction: {:x?} at {:x?}",
-                        params.instruction, self.pc
-                    );
-                }
-            },
-            0x1 => {
-                // JP nnn
-                self.pc = params.nnn;
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn set_i(&mut self, i: u16) {
+        self.i = i;
+    }
+
+    #[inline(always)]
+    pub fn execute(&mut self, bus: &mut Bus, instruction: Instruction) {
+        match instruction {
+            Instruction::Cls => {
+                bus.clear_screen();
+            }
+            Instruction::Ret => {
+                let addr = self.stack.pop().unwrap(); // TODO: Handle error
+                self.pc = addr;
+            }
+            Instruction::Jp(nnn) => {
+                self.pc = nnn;
             }
-            0x2 => {
-                // CALL nnn
+            Instruction::Call(nnn) => {
                 self.stack.push(self.pc).unwrap(); // TODO: Handle error
-                self.pc = params.nnn;
+                self.pc = nnn;
             }
-            0x3 => {
-                // SE vx, byte
-                if self.read_reg(params.x) == params.kk {
+            Instruction::SeVxByte { x, kk } => {
+                if self.read_reg(x) == kk {
                     self.pc += 2;
                 }
             }
-            0x4 => {
-                // SNE vx, byte
-                if self.read_reg(params.x) != params.kk {
+            Instruction::SneVxByte { x, kk } => {
+                if self.read_reg(x) != kk {
                     self.pc += 2;
                 }
             }
-            0x5 => {
-                // SE vx, vy
-                if self.read_reg(params.x) == self.read_reg(params.y) {
+            Instruction::SeVxVy { x, y } => {
+                if self.read_reg(x) == self.read_reg(y) {
                     self.pc += 2;
                 }
             }
-            0x6 => {
-                // LD vx, byte
-                self.write_reg(params.x, params.kk);
-            }
-            0x7 => {
-                // ADD vx, byte
-                self.write_reg(params.x, self.read_reg(params.x).wrapping_add(params.kk));
-            }
-            0x8 => {
-                let vx = self.read_reg(params.x);
-                let vy = self.read_reg(params.y);
-                match params.n {
-                    0x0 => {
-                        // LD vx, vy
-                        self.write_reg(params.x, vy);
-                    }
-                    0x1 => {
-                        // OR vx, vy
-                        self.write_reg(params.x, vx | vy);
-                    }
-                    0x2 => {
-                        // AND vx, vy
-                        self.write_reg(params.x, vx & vy);
-                    }
-                    0x3 => {
-                        // XOR vx, vy
-                        self.write_reg(params.x, vx ^ vy);
-                    }
-                    0x4 => {
-                        // ADD vx, vy
-                        let (value, over) = vx.overflowing_add(vy);
-                        self.write_reg(params.x, value);
-                        self.write_reg(0xf, over as u8);
-                    }
-                    0x5 => {
-                        // SUB vx, vy
-                        let (value, over) = vx.overflowing_sub(vy);
-                        self.write_reg(params.x, value);
-                        self.write_reg(0xf, !over as u8);
-                    }
-                    0x6 => {
-                        // SHR vx
-                        self.write_reg(0xf, vx & 0x1);
-                        self.write_reg(params.x, vx >> 1);
-                    }
-                    0x7 => {
-                        // SUBN vx, vy
-                        let (value, over) = vy.overflowing_sub(vx);
-                        self.write_reg(params.x, value);
-                        self.write_reg(0xf, !over as u8);
-                    }
-                    0xe => {
-                        // SHL vx
-                        self.write_reg(0xf, (vx & 0x80) >> 7);
-                        self.write_reg(params.x, vx << 1);
-                    }
-                    _ => {
-                        panic!(
-                            "Unknown 0x8xy# instruction: {:x?} at {:x?}",
-                            params.instruction, self.pc
-                        );
-                    }
-                }
+            Instruction::LdVxByte { x, kk } => {
+                self.write_reg(x, kk);
             }
-            0x9 => {
-                // SNE vx, vy
-                let vx = self.read_reg(params.x);
-                let vy = self.read_reg(params.y);
-
-                if vx != vy {
+            Instruction::AddVxByte { x, kk } => {
+                self.write_reg(x, self.read_reg(x).wrapping_add(kk));
+            }
+            Instruction::LdVxVy { x, y } => {
+                self.write_reg(x, self.read_reg(y));
+            }
+            Instruction::Or { x, y } => {
+                self.write_reg(x, self.read_reg(x) | self.read_reg(y));
+                self.reset_vf_after_logic();
+            }
+            Instruction::And { x, y } => {
+                self.write_reg(x, self.read_reg(x) & self.read_reg(y));
+                self.reset_vf_after_logic();
+            }
+            Instruction::Xor { x, y } => {
+                self.write_reg(x, self.read_reg(x) ^ self.read_reg(y));
+                self.reset_vf_after_logic();
+            }
+            Instruction::AddVxVy { x, y } => {
+                let (value, over) = self.read_reg(x).overflowing_add(self.read_reg(y));
+                self.write_reg(x, value);
+                self.write_reg(0xf, over as u8);
+            }
+            Instruction::Sub { x, y } => {
+                let (value, over) = self.read_reg(x).overflowing_sub(self.read_reg(y));
+                self.write_reg(x, value);
+                self.write_reg(0xf, !over as u8);
+            }
+            Instruction::Shr { x, y } => {
+                let value = self.shift_source(x, y);
+                self.write_reg(0xf, value & 0x1);
+                self.write_reg(x, value >> 1);
+            }
+            Instruction::Subn { x, y } => {
+                let (value, over) = self.read_reg(y).overflowing_sub(self.read_reg(x));
+                self.write_reg(x, value);
+                self.write_reg(0xf, !over as u8);
+            }
+            Instruction::Shl { x, y } => {
+                let value = self.shift_source(x, y);
+                self.write_reg(0xf, (value & 0x80) >> 7);
+                self.write_reg(x, value << 1);
+            }
+            Instruction::SneVxVy { x, y } => {
+                if self.read_reg(x) != self.read_reg(y) {
                     self.pc += 2;
                 }
             }
-            0xa => {
-                // LD I, addr
-                self.i = params.nnn;
+            Instruction::LdI(nnn) => {
+                self.i = nnn;
             }
-            0xb => {
-                // JP v0, addr
-                let v0 = self.read_reg(0);
-                self.pc = (v0 as u16).wrapping_add(params.nnn);
+            Instruction::JpV0(nnn) => {
+                let x = if self.quirks.jump_uses_vx {
+                    (nnn >> 8) as u8
+                } else {
+                    0
+                };
+                self.pc = (self.read_reg(x) as u16).wrapping_add(nnn);
             }
-            0xc => {
-                // RND vx, byte
+            Instruction::Rnd { x, kk } => {
                 let rand_number = self.rng.gen_range(0x00..0xff);
-                self.write_reg(params.x, rand_number & params.kk);
-            }
-            0xd => {
-                // DRW vx, vy, n
-                self.draw(bus, params);
-            }
-            0xe => {
-                match params.kk {
-                    0x9E => {
-                        // SKP vx
-                        let vx = self.read_reg(params.x);
-                        if bus.is_key_pressed(vx) {
-                            self.pc += 2;
-                        }
-                    }
-                    0xA1 => {
-                        // SKNP vx
-                        let vx = self.read_reg(params.x);
-                        if !bus.is_key_pressed(vx) {
-                            self.pc += 2;
-                        }
-                    }
-                    _ => {
-                        panic!(
-                            "Unknown 0xex## instruction: {:x?} at {:x?}",
-                            params.instruction, self.pc
-                        );
-                    }
-                }
+                self.write_reg(x, rand_number & kk);
             }
-            0xf => match params.kk {
-                0x07 => {
-                    // LD vx, DT
-                    self.write_reg(params.x, self.delay_timer);
-                }
-                0x0a => {
-                    // LD vx, K
-                    if let Some(key) = bus.get_key_pressed() {
-                        self.write_reg(params.x, key);
-                    }
-                }
-                0x15 => {
-                    // LD DT, vx
-                    self.delay_timer = self.read_reg(params.x);
-                }
-                0x18 => {
-                    // LD ST, vx
-                    self.sound_timer = self.read_reg(params.x);
-                }
-                0x1e => {
-                    // ADD I, vx
-                    let vx = self.read_reg(params.x) as u16;
-                    self.i = self.i.wrapping_add(vx);
+            Instruction::Drw { x, y, n } => {
+                self.draw(bus, x, y, n);
+            }
+            Instruction::Skp { x } => {
+                if bus.is_key_pressed(self.read_reg(x)) {
+                    self.pc += 2;
                 }
-                0x29 => {
-                    // LD F, vx
-                    self.i = self.read_reg(params.x) as u16 * 5;
+            }
+            Instruction::Sknp { x } => {
+                if !bus.is_key_pressed(self.read_reg(x)) {
+                    self.pc += 2;
                 }
-                0x33 => {
-                    // LD B, vx
-                    let vx = self.read_reg(params.x);
-                    bus.write_ram(&[vx / 100, (vx % 100) / 10, vx % 10], self.i);
+            }
+            Instruction::LdVxDt { x } => {
+                self.write_reg(x, self.delay_timer);
+            }
+            Instruction::LdVxK { x } => {
+                if let Some(key) = bus.get_key_pressed() {
+                    self.write_reg(x, key);
                 }
-                0x55 => {
-                    // LD [I], vx
-                    for index in 0..=params.x {
-                        let vx = self.read_reg(index);
-                        bus.write_ram(&[vx], self.i + index as u16);
+            }
+            Instruction::LdDtVx { x } => {
+                self.delay_timer = self.read_reg(x);
+            }
+            Instruction::LdStVx { x } => {
+                self.sound_timer = self.read_reg(x);
+            }
+            Instruction::AddIVx { x } => {
+                let vx = self.read_reg(x) as u16;
+                self.i = self.i.wrapping_add(vx);
+            }
+            Instruction::LdFVx { x } => {
+                let glyph = (self.read_reg(x) & 0xf) as u16;
+                self.i = self.memory_map.font_address + glyph * 5;
+            }
+            Instruction::LdBVx { x } => {
+                let vx = self.read_reg(x);
+                self.store(bus, &[vx / 100, (vx % 100) / 10, vx % 10], self.i);
+            }
+            Instruction::LdIVx { x } => {
+                for index in 0..=x {
+                    let vx = self.read_reg(index);
+                    if !self.store(bus, &[vx], self.i + index as u16) {
+                        return;
                     }
-                    self.i += params.x as u16 + 1;
                 }
-                0x65 => {
-                    // LD vx, [I]
-                    for index in 0..=params.x {
-                        let value = bus.read_ram(self.i + index as u16);
-                        self.write_reg(index, value);
-                    }
-                    self.i += params.x as u16 + 1;
+                if self.quirks.load_store_increments_i {
+                    self.i += x as u16 + 1;
                 }
-                _ => {
-                    panic!(
-                        "Unknown 0xfx## instruction: {:x?} at {:x?}",
-                        params.instruction, self.pc
-                    );
+            }
+            Instruction::LdVxI { x } => {
+                for index in 0..=x {
+                    let value = bus.read_ram(self.i + index as u16);
+                    self.write_reg(index, value);
                 }
-            },
-            _ => panic!(
-                "Unknown instruction: {:x?} at {:x?}",
-                params.instruction, self.pc
-            ),
+                if self.quirks.load_store_increments_i {
+                    self.i += x as u16 + 1;
+                }
+            }
         }
     }
 
-    fn write_reg(&mut self, x: u8, value: u8) {
-        self.vx[x as usize] = value;
-    }
+    /// Returns false when the write faulted and didn't happen
+    fn store(&mut self, bus: &mut Bus, data: &[u8], address: u16) -> bool {
+        let reserved = (0..data.len() as u16)
+            .map(|offset| address.wrapping_add(offset))
+            .find(|&address| self.memory_map.is_reserved(address));
+        match (self.memory_map.reserved_writes, reserved) {
+            (ReservedWrites::Log, Some(address)) => {
+                eprintln!(
+                    "Write to reserved memory at {:x?} from {:x?}",
+                    address, self.pc
+                );
+            }
+            (ReservedWrites::Fault, Some(address)) => {
+                self.pc = self.pc.wrapping_sub(2);
+                self.fault = Some(Fault::ReservedWrite {
+                    address,
+                    pc: self.pc,
+                });
+                return false;
+            }
+            _ => {}
+        }
 
-    fn read_reg(&self, x: u8) -> u8 {
-        self.vx[x as usize]
+        bus.write_ram(data, address);
+        true
     }
 
-    fn fetch_instruction(&mut self, bus: &mut Bus) -> u16 {
-        let hi = bus.read_ram(self.pc) as u16;
-        let lo = bus.read_ram(self.pc.wrapping_add(1)) as u16;
-        self.pc = self.pc.wrapping_add(2);
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.read_reg(y)
+        } else {
+            self.read_reg(x)
+        }
+    }
 
-        hi << 8 | lo
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.write_reg(0xf, 0);
+        }
     }
 
-    fn parse_instruction(instruction: u16) -> InstructionData {
-        let nnn = instruction & 0x0fff;
-        let kk = (instruction & 0x00ff) as u8;
-        let x = ((instruction & 0x0f00) >> 8) as u8;
-        let y = ((instruction & 0x00f0) >> 4) as u8;
-        let n = (instruction & 0x000f) as u8;
+    pub fn write_reg(&mut self, x: u8, value: u8) {
+        self.vx[x as usize] = value;
+    }
 
-        InstructionData {
-            instruction,
-            nnn,
-            kk,
-            x,
-            y,
-            n,
-        }
+    pub fn read_reg(&self, x: u8) -> u8 {
+        self.vx[x as usize]
     }
 
-    fn draw(&mut self, bus: &mut Bus, params: InstructionData) {
-        let x = self.read_reg(params.x);
-        let y = self.read_reg(params.y);
-        let height = params.n;
+    pub fn draw(&mut self, bus: &mut Bus, x: u8, y: u8, height: u8) {
+        let x = self.read_reg(x);
+        let y = self.read_reg(y);
 
         let has_collision = (0..height).fold(false, |flag, i| {
             let byte = bus.read_ram(self.i.wrapping_add(i as u16));
@@ -335,33 +372,43 @@ impl Cpu {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ENTRY_POINT;
 
     #[test]
     fn fetch_instruction() {
-        let mut cpu = Cpu::new();
+        let cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
         bus.write_ram(&[0x00, 0xE0], 0x200);
 
-        let instruction = cpu.fetch_instruction(&mut bus);
+        let instruction = cpu.decode(&mut bus);
 
-        assert_eq!(instruction, 0x00e0);
+        assert_eq!(instruction, Instruction::Cls);
     }
 
     #[test]
-    fn parse_instruction() {
-        let params = Cpu::parse_instruction(0x1234);
+    fn self_modifying_code() {
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
+        let mut bus = Bus::new();
+        bus.write_ram(
+            &[
+                0x61, 0x01, // 0x0200: ld v1, 0x01
+                0x12, 0x00, // 0x0202: jp 0x200
+            ],
+            ENTRY_POINT,
+        );
 
-        assert_eq!(params.instruction, 0x1234);
-        assert_eq!(params.nnn, 0x0234);
-        assert_eq!(params.kk, 0x34);
-        assert_eq!(params.x, 0x2);
-        assert_eq!(params.y, 0x3);
-        assert_eq!(params.n, 0x4);
+        cpu.run(&mut bus);
+        cpu.run(&mut bus);
+        assert_eq!(cpu.vx[1], 0x01);
+
+        bus.write_ram(&[0x02], ENTRY_POINT + 1);
+        cpu.run(&mut bus);
+        assert_eq!(cpu.vx[1], 0x02);
     }
 
     #[test]
     fn subroutine() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
         bus.write_ram(
             &[
@@ -383,7 +430,7 @@ mod tests {
 
     #[test]
     fn skips() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
         bus.write_ram(
             &[
@@ -445,7 +492,7 @@ mod tests {
 
     #[test]
     fn inst_0x7xkk() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
 
         bus.write_ram(
@@ -470,7 +517,7 @@ mod tests {
 
     #[test]
     fn inst_0x8xyn() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
 
         bus.write_ram(
@@ -572,7 +619,7 @@ mod tests {
 
     #[test]
     fn inst_0xannn() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
         bus.write_ram(
             &[
@@ -590,7 +637,7 @@ mod tests {
 
     #[test]
     fn inst_0xbnnn() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
         bus.write_ram(
             &[
@@ -609,9 +656,128 @@ mod tests {
         assert_eq!(cpu.pc, 0x0410);
     }
 
+    #[test]
+    fn quirks() {
+        let quirks = Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            logic_resets_vf: true,
+        };
+        let mut cpu = Cpu::new(quirks, MemoryMap::default(), 0);
+        let mut bus = Bus::new();
+        bus.write_ram(
+            &[
+                0x62, 0x81, // 0x0200: ld v2, 0x81
+                0x81, 0x26, // 0x0202: shr v1, v2  (v1 = 0x40, vf = 1)
+                0x81, 0x21, // 0x0204: or v1, v2   (v1 = 0xc1, vf = 0)
+                0xa3, 0x00, // 0x0206: ld I, 0x300
+                0xf1, 0x55, // 0x0208: ld [I], v1
+                0xb2, 0x10, // 0x020a: jp v2, 0x210 (to 0x291)
+            ],
+            ENTRY_POINT,
+        );
+
+        cpu.run(&mut bus);
+        cpu.run(&mut bus);
+        assert_eq!(cpu.vx[1], 0x40);
+        assert_eq!(cpu.vx[0xf], 0x1);
+
+        cpu.run(&mut bus);
+        assert_eq!(cpu.vx[1], 0xc1);
+        assert_eq!(cpu.vx[0xf], 0x0);
+
+        cpu.run(&mut bus);
+        cpu.run(&mut bus);
+        assert_eq!(cpu.i, 0x300);
+        assert_eq!(bus.read_ram(0x301), 0xc1);
+
+        cpu.run(&mut bus);
+        assert_eq!(cpu.pc, 0x0291);
+    }
+
+    #[test]
+    fn font_address() {
+        let memory_map = MemoryMap {
+            font_address: 0x050,
+            ..MemoryMap::default()
+        };
+        let mut cpu = Cpu::new(Quirks::default(), memory_map, 0);
+        let mut bus = Bus::new();
+        bus.write_ram(
+            &[
+                0x61, 0x0a, // 0x0200: ld v1, 0x0a
+                0xf1, 0x29, // 0x0202: ld F, v1
+            ],
+            ENTRY_POINT,
+        );
+
+        cpu.run(&mut bus);
+        cpu.run(&mut bus);
+        assert_eq!(cpu.i, 0x050 + 0x0a * 5);
+    }
+
+    #[test]
+    fn reserved_write_allowed() {
+        let memory_map = MemoryMap {
+            reserved_writes: ReservedWrites::Log,
+            ..MemoryMap::default()
+        };
+        let mut cpu = Cpu::new(Quirks::default(), memory_map, 0);
+        let mut bus = Bus::new();
+        bus.write_ram(
+            &[
+                0x60, 0xff, // 0x0200: ld v0, 0xff
+                0xa1, 0x00, // 0x0202: ld I, 0x100
+                0xf0, 0x33, // 0x0204: ld B, v0
+            ],
+            ENTRY_POINT,
+        );
+
+        cpu.run(&mut bus);
+        cpu.run(&mut bus);
+        cpu.run(&mut bus);
+        assert_eq!(bus.read_ram(0x100), 2);
+        assert_eq!(bus.read_ram(0x101), 5);
+        assert_eq!(bus.read_ram(0x102), 5);
+    }
+
+    #[test]
+    fn reserved_write_fault() {
+        let memory_map = MemoryMap {
+            reserved_writes: ReservedWrites::Fault,
+            ..MemoryMap::default()
+        };
+        let mut cpu = Cpu::new(Quirks::default(), memory_map, 0);
+        let mut bus = Bus::new();
+        bus.write_ram(
+            &[
+                0x60, 0x01, // 0x0200: ld v0, 0x01
+                0xa1, 0xff, // 0x0202: ld I, 0x1ff
+                0xf1, 0x55, // 0x0204: ld [I], v1
+            ],
+            ENTRY_POINT,
+        );
+
+        for _ in 0..5 {
+            cpu.run(&mut bus);
+        }
+        let fault = Fault::ReservedWrite {
+            address: 0x1ff,
+            pc: 0x204,
+        };
+        assert_eq!(cpu.fault(), Some(fault));
+        assert_eq!(cpu.pc, 0x204);
+        assert_eq!(cpu.i, 0x1ff);
+        assert_eq!(bus.read_ram(0x1ff), 0);
+
+        cpu.reset(0);
+        assert_eq!(cpu.fault(), None);
+    }
+
     #[test]
     fn draw() {
-        let mut cpu = Cpu::new();
+        let mut cpu = Cpu::new(Quirks::default(), MemoryMap::default(), 0);
         let mut bus = Bus::new();
         bus.write_ram(
             &[
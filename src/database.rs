@@ -0,0 +1,349 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{Palette, Quirks, CYCLES_PER_FRAME, MAX_CYCLES_PER_FRAME};
+
+const EMBEDDED: &str = include_str!("../data/programs.json");
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    InvalidColor(String),
+}
+
+impl From<io::Error> for DatabaseError {
+    fn from(error: io::Error) -> Self {
+        DatabaseError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for DatabaseError {
+    fn from(error: serde_json::Error) -> Self {
+        DatabaseError::Json(error)
+    }
+}
+
+/// What the database knows about one ROM image
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    /// The first listed platform this emulator has quirks for
+    pub platform: Option<String>,
+    pub quirks: Option<Quirks>,
+    /// Instructions per frame
+    pub tickrate: Option<usize>,
+    pub palette: Option<Palette>,
+    /// Which CHIP-8 key does what, e.g. "up" -> 5
+    pub keys: BTreeMap<String, u8>,
+}
+
+impl RomInfo {
+    /// The tickrate, or the default when the database doesn't have one
+    pub fn cycles_per_frame(&self) -> usize {
+        self.tickrate.map_or(CYCLES_PER_FRAME, |tickrate| {
+            tickrate.clamp(1, MAX_CYCLES_PER_FRAME)
+        })
+    }
+}
+
+/// ROM metadata keyed by the SHA-1 of the program, read from the
+/// `programs.json` format of the community chip-8-database
+pub struct RomDatabase {
+    roms: HashMap<String, RomInfo>,
+}
+
+impl RomDatabase {
+    /// The small database built into the emulator
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED).expect("Embedded ROM database is invalid")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DatabaseError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(json: &str) -> Result<Self, DatabaseError> {
+        let programs: Vec<ProgramEntry> = serde_json::from_str(json)?;
+
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (hash, rom) in program.roms {
+                let info = rom.into_info(&program.title)?;
+                roms.insert(hash.to_lowercase(), info);
+            }
+        }
+        Ok(Self { roms })
+    }
+
+    /// Adds the other database's ROMs, replacing entries for the same hash
+    pub fn merge(&mut self, other: RomDatabase) {
+        self.roms.extend(other.roms);
+    }
+
+    pub fn lookup(&self, hash: &str) -> Option<&RomInfo> {
+        self.roms.get(&hash.to_lowercase())
+    }
+
+    pub fn lookup_program(&self, program: &[u8]) -> Option<&RomInfo> {
+        self.lookup(&sha1_smol::Sha1::from(program).digest().to_string())
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+struct ProgramEntry {
+    title: String,
+    #[serde(default)]
+    roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RomEntry {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, QuirkEntry>,
+    tickrate: Option<usize>,
+    colors: Option<ColorsEntry>,
+    #[serde(default)]
+    keys: BTreeMap<String, u8>,
+}
+
+/// Quirks in the database's terms. Anything missing is taken from the
+/// platform
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct QuirkEntry {
+    shift: Option<bool>,
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    jump: Option<bool>,
+    logic: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct ColorsEntry {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+impl RomEntry {
+    fn into_info(self, title: &str) -> Result<RomInfo, DatabaseError> {
+        let platform = self
+            .platforms
+            .into_iter()
+            .find(|platform| platform_quirks(platform).is_some());
+
+        let quirky_platforms = self.quirky_platforms;
+        let quirks = platform.as_ref().map(|platform| {
+            let base = platform_quirks(platform).unwrap();
+            let overrides = quirky_platforms.get(platform).copied().unwrap_or_default();
+            to_quirks(base, overrides)
+        });
+
+        let palette = match self.colors {
+            Some(colors) if !colors.pixels.is_empty() => Some(to_palette(&colors.pixels)?),
+            _ => None,
+        };
+
+        Ok(RomInfo {
+            title: title.to_string(),
+            platform,
+            quirks,
+            tickrate: self.tickrate,
+            palette,
+            keys: self.keys,
+        })
+    }
+}
+
+/// The quirks of the platforms in the database's `platforms.json`
+fn platform_quirks(platform: &str) -> Option<QuirkEntry> {
+    let (shift, memory_increment_by_x, memory_leave_i_unchanged, jump, logic) = match platform {
+        "originalChip8" | "hybridVIP" => (false, false, false, false, true),
+        "modernChip8" | "xochip" => (false, false, false, false, false),
+        "chip48" | "superchip1" => (true, true, false, true, false),
+        "superchip" => (true, false, true, true, false),
+        _ => return None,
+    };
+    Some(QuirkEntry {
+        shift: Some(shift),
+        memory_increment_by_x: Some(memory_increment_by_x),
+        memory_leave_i_unchanged: Some(memory_leave_i_unchanged),
+        jump: Some(jump),
+        logic: Some(logic),
+    })
+}
+
+/// Only I moving to past the last register or staying put can be emulated,
+/// so incrementing by x counts as staying put
+fn to_quirks(base: QuirkEntry, overrides: QuirkEntry) -> Quirks {
+    let get = |value: Option<bool>, base: Option<bool>| value.or(base).unwrap_or(false);
+
+    let shift = get(overrides.shift, base.shift);
+    let increment_by_x = get(overrides.memory_increment_by_x, base.memory_increment_by_x);
+    let leave_i_unchanged = get(
+        overrides.memory_leave_i_unchanged,
+        base.memory_leave_i_unchanged,
+    );
+
+    Quirks {
+        shift_uses_vy: !shift,
+        load_store_increments_i: !increment_by_x && !leave_i_unchanged,
+        jump_uses_vx: get(overrides.jump, base.jump),
+        logic_resets_vf: get(overrides.logic, base.logic),
+    }
+}
+
+/// Colours missing from the list come from the classic palette
+fn to_palette(pixels: &[String]) -> Result<Palette, DatabaseError> {
+    let mut colors = Palette::CLASSIC.colors;
+    for (color, hex) in colors.iter_mut().zip(pixels) {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let rgb = match digits.len() {
+            6 => u32::from_str_radix(digits, 16).ok(),
+            _ => None,
+        };
+        *color = 0xff000000 | rgb.ok_or_else(|| DatabaseError::InvalidColor(hex.clone()))?;
+    }
+    Ok(Palette {
+        name: "database",
+        colors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::PONG;
+
+    const JSON: &str = r##"[
+        {
+            "title": "Game",
+            "authors": ["Someone"],
+            "roms": {
+                "AABBCC": {
+                    "file": "game.ch8",
+                    "platforms": ["megachip8", "superchip"],
+                    "quirkyPlatforms": { "superchip": { "jump": false } },
+                    "tickrate": 30,
+                    "colors": { "pixels": ["#102030", "#ffffff"] },
+                    "keys": { "left": 4, "right": 6 }
+                },
+                "ddeeff": {}
+            }
+        }
+    ]"##;
+
+    #[test]
+    fn lookup() {
+        let database = RomDatabase::parse(JSON).unwrap();
+        assert_eq!(database.len(), 2);
+
+        let info = database.lookup("aabbcc").unwrap();
+        assert_eq!(info.title, "Game");
+        assert_eq!(info.platform.as_deref(), Some("superchip"));
+        assert_eq!(
+            info.quirks,
+            Some(Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+            })
+        );
+        assert_eq!(info.tickrate, Some(30));
+        let palette = info.palette.unwrap();
+        assert_eq!(palette.colors[..2], [0xff102030, 0xffffffff]);
+        assert_eq!(info.keys["right"], 6);
+
+        let bare = database.lookup("DDEEFF").unwrap();
+        assert_eq!(
+            (bare.quirks, bare.tickrate, bare.palette),
+            (None, None, None)
+        );
+        assert!(database.lookup("123456").is_none());
+    }
+
+    #[test]
+    fn original_chip8_quirks() {
+        let quirks = to_quirks(
+            platform_quirks("originalChip8").unwrap(),
+            QuirkEntry::default(),
+        );
+        assert_eq!(
+            quirks,
+            Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: true,
+            }
+        );
+    }
+
+    #[test]
+    fn merge_overrides() {
+        let mut database = RomDatabase::parse(JSON).unwrap();
+        let user = r#"[{ "title": "Renamed", "roms": { "aabbcc": { "tickrate": 5 } } }]"#;
+        database.merge(RomDatabase::parse(user).unwrap());
+
+        let info = database.lookup("aabbcc").unwrap();
+        assert_eq!(info.title, "Renamed");
+        assert_eq!(info.tickrate, Some(5));
+        assert_eq!(database.len(), 2);
+    }
+
+    #[test]
+    fn invalid_color() {
+        let json =
+            r##"[{ "title": "Bad", "roms": { "aa": { "colors": { "pixels": ["#12"] } } } }]"##;
+        assert!(matches!(
+            RomDatabase::parse(json),
+            Err(DatabaseError::InvalidColor(_))
+        ));
+    }
+
+    #[test]
+    fn embedded_knows_pong() {
+        let program = PONG;
+        let info = RomDatabase::embedded().lookup_program(program).cloned();
+        assert_eq!(info.map(|info| info.title), Some("Pong".to_string()));
+    }
+
+    #[test]
+    fn rom_info_configures_chip8() {
+        let program = PONG;
+        let database = RomDatabase::embedded();
+        let info = database.lookup_program(program).unwrap();
+        let chip8 = crate::Chip8::builder().rom_info(info).build();
+
+        let original = to_quirks(
+            platform_quirks("originalChip8").unwrap(),
+            QuirkEntry::default(),
+        );
+        assert_eq!(chip8.quirks(), original);
+        assert_ne!(chip8.quirks(), Quirks::default());
+        assert_eq!(info.cycles_per_frame(), 8);
+
+        let mut fast = info.clone();
+        fast.tickrate = Some(5000);
+        assert_eq!(fast.cycles_per_frame(), MAX_CYCLES_PER_FRAME);
+        fast.tickrate = None;
+        assert_eq!(fast.cycles_per_frame(), CYCLES_PER_FRAME);
+    }
+}
@@ -0,0 +1,34 @@
+//! Programs and helpers shared by the unit tests
+
+use crate::{Chip8, CYCLES_PER_FRAME};
+
+pub const PONG: &[u8] = include_bytes!("../roms/pong.ch8");
+pub const TEST_OPCODE: &[u8] = include_bytes!("../roms/test_opcode.ch8");
+
+// I = 0x300, V0 = [I], V0 += 1, I = 0x300, [I] = V0, loop, so the byte at
+// 0x300 goes up by one every six instructions
+#[rustfmt::skip]
+pub const COUNTER: [u8; 12] = [
+    0xa3, 0x00, 0xf0, 0x65, 0x70, 0x01, 0xa3, 0x00, 0xf0, 0x55, 0x12, 0x00,
+];
+
+/// Key masks that move pong's paddle up and down
+pub fn paddle(frames: u16) -> Vec<u16> {
+    (0..frames)
+        .map(|frame| if frame % 40 < 20 { 1 << 1 } else { 1 << 4 })
+        .collect()
+}
+
+/// Runs a frame per key mask and returns the screen
+pub fn play(chip8: &mut Chip8, keys: &[u16], cycles_per_frame: usize) -> Vec<u8> {
+    for &keys in keys {
+        chip8.set_key_mask(keys);
+        chip8.run_frame(cycles_per_frame);
+    }
+    chip8.get_framebuffer().to_vec()
+}
+
+/// Pong for a few seconds of paddle movement
+pub fn play_pong(chip8: &mut Chip8) -> Vec<u8> {
+    play(chip8, &paddle(300), CYCLES_PER_FRAME)
+}
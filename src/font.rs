@@ -0,0 +1,258 @@
+use std::{fs, io, path::Path};
+
+pub const SMALL_GLYPH_SIZE: usize = 5;
+pub const BIG_GLYPH_SIZE: usize = 10;
+pub const SMALL_FONT_SIZE: usize = SMALL_GLYPH_SIZE * 16;
+pub const BIG_FONT_SIZE: usize = BIG_GLYPH_SIZE * 16;
+
+const CHIP48: [u8; SMALL_FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+const COSMAC_VIP: [u8; SMALL_FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+const FISH_N_CHIPS: [u8; SMALL_FONT_SIZE] = [
+    0x60, 0xA0, 0xA0, 0xA0, 0xC0, // 0
+    0x40, 0xC0, 0x40, 0x40, 0xE0, // 1
+    0xC0, 0x20, 0x40, 0x80, 0xE0, // 2
+    0xC0, 0x20, 0x40, 0x20, 0xC0, // 3
+    0x20, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xC0, 0x20, 0xC0, // 5
+    0x40, 0x80, 0xC0, 0xA0, 0x40, // 6
+    0xE0, 0x20, 0x60, 0x40, 0x40, // 7
+    0x40, 0xA0, 0x40, 0xA0, 0x40, // 8
+    0x40, 0xA0, 0x60, 0x20, 0x40, // 9
+    0x40, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xC0, 0xA0, 0xC0, // B
+    0x60, 0x80, 0x80, 0x80, 0x60, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xC0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+// SUPER-CHIP 1.1 only ships big glyphs for the digits
+const SCHIP_BIG: [u8; BIG_GLYPH_SIZE * 10] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
+const OCTO_BIG: [u8; BIG_FONT_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+const FISH_N_CHIPS_BIG: [u8; BIG_FONT_SIZE] = [
+    0x7C, 0xC6, 0xCE, 0xDE, 0xD6, 0xF6, 0xE6, 0xC6, 0x7C, 0x00, // 0
+    0x10, 0x30, 0xF0, 0x30, 0x30, 0x30, 0x30, 0x30, 0xFC, 0x00, // 1
+    0x78, 0xCC, 0xCC, 0x0C, 0x18, 0x30, 0x60, 0xCC, 0xFC, 0x00, // 2
+    0x78, 0xCC, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0xCC, 0x78, 0x00, // 3
+    0x0C, 0x1C, 0x3C, 0x6C, 0xCC, 0xFE, 0x0C, 0x0C, 0x1E, 0x00, // 4
+    0xFC, 0xC0, 0xC0, 0xC0, 0xF8, 0x0C, 0x0C, 0xCC, 0x78, 0x00, // 5
+    0x38, 0x60, 0xC0, 0xC0, 0xF8, 0xCC, 0xCC, 0xCC, 0x78, 0x00, // 6
+    0xFE, 0xC6, 0xC6, 0x06, 0x0C, 0x18, 0x30, 0x30, 0x30, 0x00, // 7
+    0x78, 0xCC, 0xCC, 0xEC, 0x78, 0xDC, 0xCC, 0xCC, 0x78, 0x00, // 8
+    0x7C, 0xC6, 0xC6, 0xC6, 0x7C, 0x18, 0x18, 0x30, 0x70, 0x00, // 9
+    0x30, 0x78, 0xCC, 0xCC, 0xCC, 0xFC, 0xCC, 0xCC, 0xCC, 0x00, // A
+    0xFC, 0x66, 0x66, 0x66, 0x7C, 0x66, 0x66, 0x66, 0xFC, 0x00, // B
+    0x3C, 0x66, 0xC6, 0xC0, 0xC0, 0xC0, 0xC6, 0x66, 0x3C, 0x00, // C
+    0xF8, 0x6C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x6C, 0xF8, 0x00, // D
+    0xFE, 0x62, 0x60, 0x64, 0x7C, 0x64, 0x60, 0x62, 0xFE, 0x00, // E
+    0xFE, 0x66, 0x62, 0x64, 0x7C, 0x64, 0x60, 0x60, 0xF0, 0x00, // F
+];
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    BadSize(usize),
+}
+
+impl From<io::Error> for FontError {
+    fn from(error: io::Error) -> Self {
+        FontError::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    small: Vec<u8>,
+    big: Vec<u8>,
+}
+
+impl Font {
+    pub fn cosmac_vip() -> Self {
+        Self::new(&COSMAC_VIP, &[])
+    }
+
+    pub fn chip48() -> Self {
+        Self::new(&CHIP48, &[])
+    }
+
+    pub fn schip() -> Self {
+        Self::new(&CHIP48, &SCHIP_BIG)
+    }
+
+    pub fn octo() -> Self {
+        Self::new(&CHIP48, &OCTO_BIG)
+    }
+
+    pub fn fish_n_chips() -> Self {
+        Self::new(&FISH_N_CHIPS, &FISH_N_CHIPS_BIG)
+    }
+
+    /// vip, chip48, schip, octo or fish
+    pub fn preset(name: &str) -> Option<Font> {
+        match name {
+            "vip" => Some(Font::cosmac_vip()),
+            "chip48" => Some(Font::chip48()),
+            "schip" => Some(Font::schip()),
+            "octo" => Some(Font::octo()),
+            "fish" => Some(Font::fish_n_chips()),
+            _ => None,
+        }
+    }
+
+    /// Parses 80 bytes of small glyphs, optionally followed by big glyphs
+    /// for either the ten digits or all sixteen characters
+    pub fn from_bytes(data: &[u8]) -> Result<Self, FontError> {
+        let (small, big) = match data.len() {
+            SMALL_FONT_SIZE => (data, &data[..0]),
+            len if len == SMALL_FONT_SIZE + BIG_GLYPH_SIZE * 10
+                || len == SMALL_FONT_SIZE + BIG_FONT_SIZE =>
+            {
+                data.split_at(SMALL_FONT_SIZE)
+            }
+            len => return Err(FontError::BadSize(len)),
+        };
+
+        Ok(Self::new(small, big))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FontError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn small(&self) -> &[u8] {
+        &self.small
+    }
+
+    pub fn big(&self) -> &[u8] {
+        &self.big
+    }
+
+    fn new(small: &[u8], big: &[u8]) -> Self {
+        Self {
+            small: small.to_vec(),
+            big: big.to_vec(),
+        }
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::chip48()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip8, MemoryMap};
+
+    #[test]
+    fn presets() {
+        let presets = [
+            (Font::cosmac_vip(), 0),
+            (Font::chip48(), 0),
+            (Font::schip(), 100),
+            (Font::octo(), BIG_FONT_SIZE),
+            (Font::fish_n_chips(), BIG_FONT_SIZE),
+        ];
+
+        for (font, big_size) in presets.iter() {
+            assert_eq!(font.small().len(), SMALL_FONT_SIZE);
+            assert_eq!(font.big().len(), *big_size);
+        }
+    }
+
+    #[test]
+    fn from_bytes() {
+        let data = [0xaa; SMALL_FONT_SIZE + BIG_FONT_SIZE];
+
+        let font = Font::from_bytes(&data).unwrap();
+        assert_eq!(font.small(), &data[..SMALL_FONT_SIZE]);
+        assert_eq!(font.big(), &data[SMALL_FONT_SIZE..]);
+
+        let font = Font::from_bytes(&data[..SMALL_FONT_SIZE]).unwrap();
+        assert!(font.big().is_empty());
+
+        let result = Font::from_bytes(&data[..SMALL_FONT_SIZE + 1]);
+        assert!(matches!(result, Err(FontError::BadSize(81))));
+    }
+
+    #[test]
+    fn loaded_into_memory() {
+        let memory_map = MemoryMap::default();
+        let chip8 = Chip8::builder()
+            .memory_map(memory_map)
+            .font(Font::fish_n_chips())
+            .build();
+
+        let glyph_1 = memory_map.font_address + SMALL_GLYPH_SIZE as u16;
+        assert_eq!(chip8.bus.read_ram(glyph_1), 0x40);
+        assert_eq!(chip8.bus.read_ram(memory_map.big_font_address), 0x7c);
+    }
+}
@@ -1,18 +1,56 @@
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl DirtyRect {
+    fn full() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: WIDTH,
+            height: HEIGHT,
+        }
+    }
+
+    fn extend(self, x: usize, y: usize) -> Self {
+        let left = self.x.min(x);
+        let top = self.y.min(y);
+        let right = (self.x + self.width).max(x + 1);
+        let bottom = (self.y + self.height).max(y + 1);
+
+        Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
 
 pub struct Framebuffer {
     buffer: [u8; WIDTH * HEIGHT],
+    dirty: Option<DirtyRect>,
 }
 
 impl Framebuffer {
     pub fn new() -> Self {
         Self {
             buffer: [0; WIDTH * HEIGHT],
+            dirty: None,
         }
     }
 
     pub fn clear(&mut self) {
+        if self.buffer.iter().any(|&pixel| pixel != 0) {
+            self.dirty = Some(DirtyRect::full());
+        }
         self.buffer = [0; WIDTH * HEIGHT];
     }
 
@@ -20,6 +58,15 @@ impl Framebuffer {
         &self.buffer
     }
 
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    /// Returns the area changed since the previous call, if any
+    pub fn take_dirty(&mut self) -> Option<DirtyRect> {
+        self.dirty.take()
+    }
+
     pub fn draw(&mut self, x: u8, y: u8, byte: u8) -> bool {
         let mut has_collision = false;
         let mut x = x as usize;
@@ -36,7 +83,10 @@ impl Framebuffer {
                 has_collision = true;
             }
 
-            self.buffer[index] ^= bit;
+            if bit == 1 {
+                self.buffer[index] ^= 1;
+                self.mark_dirty(x, y);
+            }
 
             x += 1;
             byte <<= 1;
@@ -45,6 +95,16 @@ impl Framebuffer {
         has_collision
     }
 
+    fn mark_dirty(&mut self, x: usize, y: usize) {
+        let rect = DirtyRect {
+            x,
+            y,
+            width: 1,
+            height: 1,
+        };
+        self.dirty = Some(self.dirty.map_or(rect, |dirty| dirty.extend(x, y)));
+    }
+
     fn xy_to_index(x: usize, y: usize) -> usize {
         y * WIDTH + x
     }
@@ -115,6 +175,38 @@ mod tests {
         assert_eq!(actual, &[1, 1, 1, 0, 0, 0, 0, 1]);
     }
 
+    #[test]
+    fn dirty_rect() {
+        let mut framebuffer = Framebuffer::new();
+        assert_eq!(framebuffer.take_dirty(), None);
+
+        framebuffer.draw(10, 3, 0b01000000);
+        framebuffer.draw(4, 7, 0b00010010);
+        let expected = DirtyRect {
+            x: 7,
+            y: 3,
+            width: 5,
+            height: 5,
+        };
+        assert_eq!(framebuffer.take_dirty(), Some(expected));
+        assert_eq!(framebuffer.take_dirty(), None);
+
+        framebuffer.draw(0, 0, 0);
+        assert!(!framebuffer.is_dirty());
+    }
+
+    #[test]
+    fn dirty_clear() {
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.clear();
+        assert_eq!(framebuffer.take_dirty(), None);
+
+        framebuffer.draw(0, 0, 0b10000000);
+        framebuffer.take_dirty();
+        framebuffer.clear();
+        assert_eq!(framebuffer.take_dirty(), Some(DirtyRect::full()));
+    }
+
     #[test]
     fn xy_to_index() {
         let x = 4;
@@ -0,0 +1,163 @@
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+    /// An instruction fetched for execution, whether or not it was cached
+    Fetch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub address: u16,
+    /// The byte read or written, or the whole opcode for fetches
+    pub value: u16,
+}
+
+impl MemoryAccess {
+    pub(crate) fn new(kind: AccessKind, address: u16, value: u16) -> Self {
+        Self {
+            kind,
+            address,
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+type Callback = Box<dyn FnMut(MemoryAccess)>;
+
+struct Hook {
+    id: HookId,
+    kind: AccessKind,
+    range: RangeInclusive<u16>,
+    callback: Callback,
+}
+
+/// Callbacks for memory accesses in address ranges, for debuggers, cheat
+/// finders, coverage and the like
+#[derive(Default)]
+pub(crate) struct Hooks {
+    hooks: Vec<Hook>,
+    next_id: u64,
+}
+
+impl Hooks {
+    pub fn add(
+        &mut self,
+        kind: AccessKind,
+        range: RangeInclusive<u16>,
+        callback: Callback,
+    ) -> HookId {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.hooks.push(Hook {
+            id,
+            kind,
+            range,
+            callback,
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: HookId) -> bool {
+        let len = self.hooks.len();
+        self.hooks.retain(|hook| hook.id != id);
+        self.hooks.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn notify(&mut self, access: MemoryAccess) {
+        for hook in &mut self.hooks {
+            if hook.kind == access.kind && hook.range.contains(&access.address) {
+                (hook.callback)(access);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::COUNTER, Backend, Chip8};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn log(
+        chip8: &mut Chip8,
+        kind: AccessKind,
+        range: RangeInclusive<u16>,
+    ) -> Rc<RefCell<Vec<MemoryAccess>>> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&log);
+        chip8.add_memory_hook(kind, range, move |access| sink.borrow_mut().push(access));
+        log
+    }
+
+    #[test]
+    fn reads_and_writes() {
+        let mut chip8 = Chip8::new();
+        chip8.set_program(&COUNTER);
+        let reads = log(&mut chip8, AccessKind::Read, 0x300..=0x300);
+        let writes = log(&mut chip8, AccessKind::Write, 0x300..=0x3ff);
+
+        chip8.run_cycles(12);
+
+        assert_eq!(
+            *reads.borrow(),
+            [
+                MemoryAccess::new(AccessKind::Read, 0x300, 0),
+                MemoryAccess::new(AccessKind::Read, 0x300, 1)
+            ]
+        );
+        assert_eq!(
+            *writes.borrow(),
+            [
+                MemoryAccess::new(AccessKind::Write, 0x300, 1),
+                MemoryAccess::new(AccessKind::Write, 0x300, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn fetches_include_cached_instructions() {
+        for backend in [Backend::Interpreter, Backend::Recompiler] {
+            let mut chip8 = Chip8::builder().backend(backend).build();
+            chip8.set_program(&COUNTER);
+            let fetches = log(&mut chip8, AccessKind::Fetch, 0x200..=0x202);
+
+            chip8.run_cycles(12);
+
+            let expected = [0x200, 0x202, 0x200, 0x202].map(|address| {
+                let opcode =
+                    u16::from_be_bytes([COUNTER[address - 0x200], COUNTER[address - 0x1ff]]);
+                MemoryAccess::new(AccessKind::Fetch, address as u16, opcode)
+            });
+            assert_eq!(*fetches.borrow(), expected);
+        }
+    }
+
+    #[test]
+    fn remove_hook() {
+        let mut chip8 = Chip8::new();
+        chip8.set_program(&COUNTER);
+        let count = Rc::new(RefCell::new(0));
+        let sink = Rc::clone(&count);
+        let id = chip8.add_memory_hook(AccessKind::Fetch, 0x000..=0xfff, move |_| {
+            *sink.borrow_mut() += 1
+        });
+
+        chip8.run_cycles(3);
+        assert!(chip8.remove_memory_hook(id));
+        assert!(!chip8.remove_memory_hook(id));
+        chip8.run_cycles(3);
+
+        assert_eq!(*count.borrow(), 3);
+    }
+}
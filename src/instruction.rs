@@ -0,0 +1,297 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Cls,
+    Ret,
+    Jp(u16),
+    Call(u16),
+    SeVxByte { x: u8, kk: u8 },
+    SneVxByte { x: u8, kk: u8 },
+    SeVxVy { x: u8, y: u8 },
+    LdVxByte { x: u8, kk: u8 },
+    AddVxByte { x: u8, kk: u8 },
+    LdVxVy { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    AddVxVy { x: u8, y: u8 },
+    Sub { x: u8, y: u8 },
+    Shr { x: u8, y: u8 },
+    Subn { x: u8, y: u8 },
+    Shl { x: u8, y: u8 },
+    SneVxVy { x: u8, y: u8 },
+    LdI(u16),
+    JpV0(u16),
+    Rnd { x: u8, kk: u8 },
+    Drw { x: u8, y: u8, n: u8 },
+    Skp { x: u8 },
+    Sknp { x: u8 },
+    LdVxDt { x: u8 },
+    LdVxK { x: u8 },
+    LdDtVx { x: u8 },
+    LdStVx { x: u8 },
+    AddIVx { x: u8 },
+    LdFVx { x: u8 },
+    LdBVx { x: u8 },
+    LdIVx { x: u8 },
+    LdVxI { x: u8 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    UnknownInstruction(u16),
+}
+
+impl Instruction {
+    pub fn decode(word: u16) -> Result<Self, DecodeError> {
+        let nnn = word & 0x0fff;
+        let kk = (word & 0x00ff) as u8;
+        let x = ((word & 0x0f00) >> 8) as u8;
+        let y = ((word & 0x00f0) >> 4) as u8;
+        let n = (word & 0x000f) as u8;
+
+        let instruction = match (word & 0xf000) >> 12 {
+            0x0 => match word {
+                0x00e0 => Instruction::Cls,
+                0x00ee => Instruction::Ret,
+                _ => return Err(DecodeError::UnknownInstruction(word)),
+            },
+            0x1 => Instruction::Jp(nnn),
+            0x2 => Instruction::Call(nnn),
+            0x3 => Instruction::SeVxByte { x, kk },
+            0x4 => Instruction::SneVxByte { x, kk },
+            0x5 if n == 0 => Instruction::SeVxVy { x, y },
+            0x6 => Instruction::LdVxByte { x, kk },
+            0x7 => Instruction::AddVxByte { x, kk },
+            0x8 => match n {
+                0x0 => Instruction::LdVxVy { x, y },
+                0x1 => Instruction::Or { x, y },
+                0x2 => Instruction::And { x, y },
+                0x3 => Instruction::Xor { x, y },
+                0x4 => Instruction::AddVxVy { x, y },
+                0x5 => Instruction::Sub { x, y },
+                0x6 => Instruction::Shr { x, y },
+                0x7 => Instruction::Subn { x, y },
+                0xe => Instruction::Shl { x, y },
+                _ => return Err(DecodeError::UnknownInstruction(word)),
+            },
+            0x9 if n == 0 => Instruction::SneVxVy { x, y },
+            0xa => Instruction::LdI(nnn),
+            0xb => Instruction::JpV0(nnn),
+            0xc => Instruction::Rnd { x, kk },
+            0xd => Instruction::Drw { x, y, n },
+            0xe => match kk {
+                0x9e => Instruction::Skp { x },
+                0xa1 => Instruction::Sknp { x },
+                _ => return Err(DecodeError::UnknownInstruction(word)),
+            },
+            0xf => match kk {
+                0x07 => Instruction::LdVxDt { x },
+                0x0a => Instruction::LdVxK { x },
+                0x15 => Instruction::LdDtVx { x },
+                0x18 => Instruction::LdStVx { x },
+                0x1e => Instruction::AddIVx { x },
+                0x29 => Instruction::LdFVx { x },
+                0x33 => Instruction::LdBVx { x },
+                0x55 => Instruction::LdIVx { x },
+                0x65 => Instruction::LdVxI { x },
+                _ => return Err(DecodeError::UnknownInstruction(word)),
+            },
+            _ => return Err(DecodeError::UnknownInstruction(word)),
+        };
+
+        Ok(instruction)
+    }
+
+    /// The opcode pattern, like "8xy4", naming the instruction's class
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::Cls => "00E0",
+            Instruction::Ret => "00EE",
+            Instruction::Jp(_) => "1nnn",
+            Instruction::Call(_) => "2nnn",
+            Instruction::SeVxByte { .. } => "3xkk",
+            Instruction::SneVxByte { .. } => "4xkk",
+            Instruction::SeVxVy { .. } => "5xy0",
+            Instruction::LdVxByte { .. } => "6xkk",
+            Instruction::AddVxByte { .. } => "7xkk",
+            Instruction::LdVxVy { .. } => "8xy0",
+            Instruction::Or { .. } => "8xy1",
+            Instruction::And { .. } => "8xy2",
+            Instruction::Xor { .. } => "8xy3",
+            Instruction::AddVxVy { .. } => "8xy4",
+            Instruction::Sub { .. } => "8xy5",
+            Instruction::Shr { .. } => "8xy6",
+            Instruction::Subn { .. } => "8xy7",
+            Instruction::Shl { .. } => "8xyE",
+            Instruction::SneVxVy { .. } => "9xy0",
+            Instruction::LdI(_) => "Annn",
+            Instruction::JpV0(_) => "Bnnn",
+            Instruction::Rnd { .. } => "Cxkk",
+            Instruction::Drw { .. } => "Dxyn",
+            Instruction::Skp { .. } => "Ex9E",
+            Instruction::Sknp { .. } => "ExA1",
+            Instruction::LdVxDt { .. } => "Fx07",
+            Instruction::LdVxK { .. } => "Fx0A",
+            Instruction::LdDtVx { .. } => "Fx15",
+            Instruction::LdStVx { .. } => "Fx18",
+            Instruction::AddIVx { .. } => "Fx1E",
+            Instruction::LdFVx { .. } => "Fx29",
+            Instruction::LdBVx { .. } => "Fx33",
+            Instruction::LdIVx { .. } => "Fx55",
+            Instruction::LdVxI { .. } => "Fx65",
+        }
+    }
+
+    pub fn encode(&self) -> u16 {
+        let xy = |f: u16, x: u8, y: u8, n: u16| f << 12 | (x as u16) << 8 | (y as u16) << 4 | n;
+        let xkk = |f: u16, x: u8, kk: u8| f << 12 | (x as u16) << 8 | kk as u16;
+
+        match *self {
+            Instruction::Cls => 0x00e0,
+            Instruction::Ret => 0x00ee,
+            Instruction::Jp(nnn) => 0x1000 | nnn,
+            Instruction::Call(nnn) => 0x2000 | nnn,
+            Instruction::SeVxByte { x, kk } => xkk(0x3, x, kk),
+            Instruction::SneVxByte { x, kk } => xkk(0x4, x, kk),
+            Instruction::SeVxVy { x, y } => xy(0x5, x, y, 0x0),
+            Instruction::LdVxByte { x, kk } => xkk(0x6, x, kk),
+            Instruction::AddVxByte { x, kk } => xkk(0x7, x, kk),
+            Instruction::LdVxVy { x, y } => xy(0x8, x, y, 0x0),
+            Instruction::Or { x, y } => xy(0x8, x, y, 0x1),
+            Instruction::And { x, y } => xy(0x8, x, y, 0x2),
+            Instruction::Xor { x, y } => xy(0x8, x, y, 0x3),
+            Instruction::AddVxVy { x, y } => xy(0x8, x, y, 0x4),
+            Instruction::Sub { x, y } => xy(0x8, x, y, 0x5),
+            Instruction::Shr { x, y } => xy(0x8, x, y, 0x6),
+            Instruction::Subn { x, y } => xy(0x8, x, y, 0x7),
+            Instruction::Shl { x, y } => xy(0x8, x, y, 0xe),
+            Instruction::SneVxVy { x, y } => xy(0x9, x, y, 0x0),
+            Instruction::LdI(nnn) => 0xa000 | nnn,
+            Instruction::JpV0(nnn) => 0xb000 | nnn,
+            Instruction::Rnd { x, kk } => xkk(0xc, x, kk),
+            Instruction::Drw { x, y, n } => xy(0xd, x, y, n as u16),
+            Instruction::Skp { x } => xkk(0xe, x, 0x9e),
+            Instruction::Sknp { x } => xkk(0xe, x, 0xa1),
+            Instruction::LdVxDt { x } => xkk(0xf, x, 0x07),
+            Instruction::LdVxK { x } => xkk(0xf, x, 0x0a),
+            Instruction::LdDtVx { x } => xkk(0xf, x, 0x15),
+            Instruction::LdStVx { x } => xkk(0xf, x, 0x18),
+            Instruction::AddIVx { x } => xkk(0xf, x, 0x1e),
+            Instruction::LdFVx { x } => xkk(0xf, x, 0x29),
+            Instruction::LdBVx { x } => xkk(0xf, x, 0x33),
+            Instruction::LdIVx { x } => xkk(0xf, x, 0x55),
+            Instruction::LdVxI { x } => xkk(0xf, x, 0x65),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jp(nnn) => write!(f, "JP {:#05x}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL {:#05x}", nnn),
+            Instruction::SeVxByte { x, kk } => write!(f, "SE V{:X}, {:#04x}", x, kk),
+            Instruction::SneVxByte { x, kk } => write!(f, "SNE V{:X}, {:#04x}", x, kk),
+            Instruction::SeVxVy { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LdVxByte { x, kk } => write!(f, "LD V{:X}, {:#04x}", x, kk),
+            Instruction::AddVxByte { x, kk } => write!(f, "ADD V{:X}, {:#04x}", x, kk),
+            Instruction::LdVxVy { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddVxVy { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::Subn { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SneVxVy { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(nnn) => write!(f, "LD I, {:#05x}", nnn),
+            Instruction::JpV0(nnn) => write!(f, "JP V0, {:#05x}", nnn),
+            Instruction::Rnd { x, kk } => write!(f, "RND V{:X}, {:#04x}", x, kk),
+            Instruction::Drw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp { x } => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LdVxDt { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdVxK { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtVx { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStVx { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIVx { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdFVx { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::LdBVx { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::LdIVx { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LdVxI { x } => write!(f, "LD V{:X}, [I]", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(Instruction::decode(0x00e0), Ok(Instruction::Cls));
+        assert_eq!(Instruction::decode(0x1234), Ok(Instruction::Jp(0x234)));
+        assert_eq!(
+            Instruction::decode(0x7a20),
+            Ok(Instruction::AddVxByte { x: 0xa, kk: 0x20 })
+        );
+        assert_eq!(
+            Instruction::decode(0xd125),
+            Ok(Instruction::Drw { x: 1, y: 2, n: 5 })
+        );
+        assert_eq!(Instruction::decode(0xf365), Ok(Instruction::LdVxI { x: 3 }));
+    }
+
+    #[test]
+    fn decode_unknown() {
+        for &word in &[0x0000, 0x0123, 0x5121, 0x800f, 0x9ab1, 0xe100, 0xf0ff] {
+            assert_eq!(
+                Instruction::decode(word),
+                Err(DecodeError::UnknownInstruction(word))
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for word in 0..=0xffff {
+            if let Ok(instruction) = Instruction::decode(word) {
+                assert_eq!(instruction.encode(), word, "{}", instruction);
+            }
+        }
+    }
+
+    #[test]
+    fn pattern_matches_encoding() {
+        for word in 0..=0xffff {
+            if let Ok(instruction) = Instruction::decode(word) {
+                let pattern = instruction.pattern();
+                let encoded = format!("{:04X}", word);
+                let fixed = pattern.chars().zip(encoded.chars());
+                assert!(
+                    fixed
+                        .filter(|(p, _)| p.is_ascii_uppercase() || p.is_ascii_digit())
+                        .all(|(p, e)| p == e),
+                    "{} {}",
+                    pattern,
+                    encoded
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Instruction::Jp(0x204).to_string(), "JP 0x204");
+        assert_eq!(
+            Instruction::LdVxByte { x: 1, kk: 0x0f }.to_string(),
+            "LD V1, 0x0f"
+        );
+        assert_eq!(Instruction::LdIVx { x: 0xa }.to_string(), "LD [I], VA");
+    }
+}
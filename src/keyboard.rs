@@ -1,22 +1,34 @@
 pub struct Keyboard {
-    key_pressed: Option<u8>,
+    keys: u16,
 }
 
 impl Keyboard {
     pub fn new() -> Self {
-        Self { key_pressed: None }
+        Self { keys: 0 }
     }
 
     pub fn set_key_pressed(&mut self, key: Option<u8>) {
-        self.key_pressed = key;
+        self.keys = key.map_or(0, |key| 1 << (key & 0xf));
     }
 
     pub fn get_key_pressed(&self) -> Option<u8> {
-        self.key_pressed
+        if self.keys == 0 {
+            None
+        } else {
+            Some(self.keys.trailing_zeros() as u8)
+        }
     }
 
     pub fn is_key_pressed(&self, key: u8) -> bool {
-        self.key_pressed.map_or(false, |pressed| pressed == key)
+        key < 16 && self.keys & (1 << key) != 0
+    }
+
+    pub fn set_key_mask(&mut self, keys: u16) {
+        self.keys = keys;
+    }
+
+    pub fn key_mask(&self) -> u16 {
+        self.keys
     }
 }
 
@@ -27,9 +39,7 @@ mod tests {
     #[test]
     fn get_key_pressed() {
         let expected = Some(4);
-        let keyboard = Keyboard {
-            key_pressed: expected,
-        };
+        let keyboard = Keyboard { keys: 1 << 4 };
 
         let actual = keyboard.get_key_pressed();
         assert_eq!(actual, expected);
@@ -48,11 +58,23 @@ mod tests {
 
     #[test]
     fn is_key_pressed() {
-        let keyboard = Keyboard {
-            key_pressed: Some(2),
-        };
+        let keyboard = Keyboard { keys: 1 << 2 };
 
         assert!(keyboard.is_key_pressed(2));
         assert!(!keyboard.is_key_pressed(5));
+        assert!(!keyboard.is_key_pressed(0x12));
+    }
+
+    #[test]
+    fn key_mask() {
+        let mut keyboard = Keyboard::new();
+        keyboard.set_key_mask(0b1000_0000_0010_0100);
+
+        assert!(keyboard.is_key_pressed(2));
+        assert!(keyboard.is_key_pressed(5));
+        assert!(keyboard.is_key_pressed(15));
+        assert!(!keyboard.is_key_pressed(4));
+        assert_eq!(keyboard.get_key_pressed(), Some(2));
+        assert_eq!(keyboard.key_mask(), 0b1000_0000_0010_0100);
     }
 }
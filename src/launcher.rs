@@ -0,0 +1,181 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    osd::{draw_text, line_height},
+    RomDatabase,
+};
+
+const EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+const HEADER: &str = "Select a ROM";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LauncherEntry {
+    pub path: PathBuf,
+    /// The database title, or the file name for unknown ROMs
+    pub title: String,
+}
+
+/// A list of ROMs to pick from, drawn with the OSD font
+pub struct Launcher {
+    entries: Vec<LauncherEntry>,
+    selected: usize,
+    scale: usize,
+}
+
+impl Launcher {
+    pub fn new(entries: Vec<LauncherEntry>, scale: usize) -> Self {
+        Self {
+            entries,
+            selected: 0,
+            scale,
+        }
+    }
+
+    /// Lists the ROMs in a directory, sorted by title. Only failing to read
+    /// the directory itself is an error, unreadable entries are skipped
+    pub fn scan<P: AsRef<Path>>(dir: P, database: &RomDatabase, scale: usize) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(error) => {
+                    eprintln!("Skipping a directory entry: {}", error);
+                    continue;
+                }
+            };
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            let is_rom = extension
+                .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+            if !is_rom || !path.is_file() {
+                continue;
+            }
+
+            let program = match fs::read(&path) {
+                Ok(program) => program,
+                Err(error) => {
+                    eprintln!("Skipping {}: {}", path.display(), error);
+                    continue;
+                }
+            };
+            let title = match database.lookup_program(&program) {
+                Some(info) => info.title.clone(),
+                None => path.file_name().unwrap().to_string_lossy().into_owned(),
+            };
+            entries.push(LauncherEntry { path, title });
+        }
+        entries.sort_by_key(|entry| entry.title.to_lowercase());
+
+        Ok(Self::new(entries, scale))
+    }
+
+    pub fn entries(&self) -> &[LauncherEntry] {
+        &self.entries
+    }
+
+    pub fn selected(&self) -> Option<&LauncherEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Draws the header and as many entries as fit, scrolled so the selected
+    /// one is visible
+    pub fn draw(&self, buffer: &mut [u32], width: usize, height: usize) {
+        let line_height = line_height(self.scale);
+        draw_text(buffer, width, height, HEADER, 0, 0, self.scale);
+
+        if self.entries.is_empty() {
+            let text = "No ROMs found";
+            draw_text(buffer, width, height, text, 0, line_height * 2, self.scale);
+            return;
+        }
+
+        let rows = (height / line_height).saturating_sub(2).max(1);
+        let first = (self.selected + 1).saturating_sub(rows);
+        for (row, entry) in self.entries.iter().skip(first).take(rows).enumerate() {
+            let marker = if first + row == self.selected {
+                '>'
+            } else {
+                ' '
+            };
+            let text = format!("{} {}", marker, entry.title);
+            let y = line_height * (row + 2);
+            draw_text(buffer, width, height, &text, 0, y, self.scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launcher(count: usize) -> Launcher {
+        let entries = (0..count)
+            .map(|i| LauncherEntry {
+                path: PathBuf::from(format!("{}.ch8", i)),
+                title: i.to_string(),
+            })
+            .collect();
+        Launcher::new(entries, 1)
+    }
+
+    #[test]
+    fn selection_stays_in_range() {
+        let mut launcher = launcher(2);
+        launcher.select_previous();
+        assert_eq!(launcher.selected().unwrap().title, "0");
+        launcher.select_next();
+        launcher.select_next();
+        assert_eq!(launcher.selected().unwrap().title, "1");
+
+        assert!(self::launcher(0).selected().is_none());
+    }
+
+    #[test]
+    fn scan_finds_roms() {
+        let launcher = Launcher::scan(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/roms"),
+            &RomDatabase::embedded(),
+            1,
+        )
+        .unwrap();
+        let titles: Vec<&str> = launcher
+            .entries()
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .collect();
+        assert_eq!(titles, ["CHIP-8 test opcode", "Pong"]);
+    }
+
+    #[test]
+    fn scrolls_to_selection() {
+        let mut launcher = launcher(20);
+        for _ in 0..19 {
+            launcher.select_next();
+        }
+
+        // Room for the header, a blank line and three entries
+        let (width, height) = (64, line_height(1) * 5);
+        let mut buffer = vec![0xff000000; width * height];
+        launcher.draw(&mut buffer, width, height);
+
+        let mut expected = vec![0xff000000; width * height];
+        for (row, text) in ["  17", "  18", "> 19"].iter().enumerate() {
+            let y = line_height(1) * (row + 2);
+            draw_text(&mut expected, width, height, text, 0, y, 1);
+        }
+        draw_text(&mut expected, width, height, HEADER, 0, 0, 1);
+        assert_eq!(buffer, expected);
+    }
+}
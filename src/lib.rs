@@ -1,50 +1,201 @@
 use bus::Bus;
 use cpu::Cpu;
-use std::path::Path;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use recompiler::Recompiler;
+use std::{ops::RangeInclusive, path::Path};
 
+pub use analysis::{BasicBlock, ControlFlowGraph, Edge, EdgeKind, Subroutine};
+pub use audio::{Audio, SampleGenerator, Tone, WavAudio, Waveform};
+pub use cheat::{Cheat, CheatError, CheatKind, CheatSet, Comparison, MemorySearch};
+pub use console::{render_braille, render_half_blocks};
+pub use cpu::Fault;
+pub use database::{DatabaseError, RomDatabase, RomInfo};
+pub use font::{Font, FontError};
+pub use framebuffer::{DirtyRect, HEIGHT, WIDTH};
+pub use hooks::{AccessKind, HookId, MemoryAccess};
+pub use instruction::{DecodeError, Instruction};
+pub use launcher::{Launcher, LauncherEntry};
+pub use memory_map::{MemoryMap, MemoryMapError, ReservedWrites};
+pub use movie::{Movie, MovieError};
+pub use osd::Osd;
+pub use palette::Palette;
+pub use persistence::{Persistence, PhosphorFilter};
+pub use profiler::{FunctionStats, Profile, Profiler};
+pub use quirks::Quirks;
+pub use scaler::{ScaleFilter, Scaler};
+pub use screenshot::{save_png, write_png, ScreenshotError};
+pub use video::{GifRecorder, VideoError, VideoRecorder, Y4mRecorder};
+
+mod analysis;
+mod audio;
 mod bus;
+mod cache;
+mod cheat;
+mod console;
 mod cpu;
+mod database;
+mod font;
 mod framebuffer;
+mod hooks;
+mod instruction;
 mod keyboard;
+mod launcher;
+mod memory_map;
+mod movie;
+mod osd;
+mod palette;
+mod persistence;
+mod profiler;
+mod quirks;
 mod ram;
+mod recompiler;
+mod scaler;
+mod screenshot;
 mod stack;
+mod text_format;
+mod video;
+
+#[cfg(test)]
+mod fixtures;
 
 pub const ENTRY_POINT: u16 = 0x200;
+pub const CYCLES_PER_FRAME: usize = 8;
+pub const MAX_CYCLES_PER_FRAME: usize = 1000;
+pub const FRAME_RATE: u32 = 60;
+
+pub struct Frame<'a> {
+    pub pixels: &'a [u8],
+    pub dirty: DirtyRect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Interpreter,
+    Recompiler,
+}
+
+/// What a hard reset leaves in RAM before the font and program are restored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamFill {
+    Zero,
+    /// Random bytes derived from the seed, so runs stay reproducible
+    Random,
+}
+
+#[derive(Default)]
+pub struct Chip8Builder {
+    backend: Backend,
+    seed: Option<u64>,
+    quirks: Quirks,
+    memory_map: MemoryMap,
+    font: Font,
+}
+
+impl Chip8Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn memory_map(mut self, memory_map: MemoryMap) -> Self {
+        self.memory_map = memory_map;
+        self
+    }
+
+    pub fn font(mut self, font: Font) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Applies the quirks the ROM database has for the program to be loaded.
+    /// The speed and colours are up to the front-end, see
+    /// `RomInfo::cycles_per_frame` and `RomInfo::palette`
+    pub fn rom_info(mut self, info: &RomInfo) -> Self {
+        if let Some(quirks) = info.quirks {
+            self.quirks = quirks;
+        }
+        self
+    }
+
+    /// A memory map that doesn't pass `MemoryMap::validate` is replaced by
+    /// the default one
+    pub fn build(self) -> Chip8 {
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let memory_map = match self.memory_map.validate() {
+            Ok(()) => self.memory_map,
+            Err(_) => MemoryMap::default(),
+        };
+
+        let mut chip8 = Chip8 {
+            bus: Bus::new(),
+            cpu: Cpu::new(self.quirks, memory_map, seed),
+            backend: self.backend,
+            recompiler: Recompiler::new(),
+            seed,
+            quirks: self.quirks,
+            memory_map,
+            font: self.font,
+            program: Vec::new(),
+        };
+        chip8.load_font();
+        chip8
+    }
+}
 
 pub struct Chip8 {
     bus: Bus,
     cpu: Cpu,
+    backend: Backend,
+    recompiler: Recompiler,
+    seed: u64,
+    quirks: Quirks,
+    memory_map: MemoryMap,
+    font: Font,
+    program: Vec<u8>,
 }
 
 impl Chip8 {
     pub fn new() -> Self {
-        let mut bus = Bus::new();
-        bus.write_ram(
-            &[
-                0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-                0x20, 0x60, 0x20, 0x20, 0x70, // 1
-                0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-                0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-                0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-                0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-                0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-                0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-                0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-                0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-                0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-                0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-                0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-                0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-                0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-                0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-            ],
-            0x0,
-        );
-
-        Self {
-            bus,
-            cpu: Cpu::new(),
-        }
+        Self::builder().build()
+    }
+
+    pub fn builder() -> Chip8Builder {
+        Chip8Builder::new()
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn memory_map(&self) -> MemoryMap {
+        self.memory_map
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
     }
 
     pub fn load_program<P: AsRef<Path>>(&mut self, path: P) {
@@ -52,22 +203,127 @@ impl Chip8 {
         self.set_program(data.as_slice());
     }
 
+    /// Whatever doesn't fit between the entry point and the end of RAM is
+    /// left out
     pub fn set_program(&mut self, data: &[u8]) {
-        self.bus.write_ram(data, ENTRY_POINT);
+        let room = ram::RAM_SIZE - self.memory_map.entry_point as usize;
+        let data = &data[..data.len().min(room)];
+        self.bus.write_ram(data, self.memory_map.entry_point);
+        self.program = data.to_vec();
+    }
+
+    /// Restarts the loaded program from its power-on state. Everything in RAM
+    /// apart from the font and the program survives, as on a real soft reset
+    pub fn reset(&mut self) {
+        self.bus.reset();
+        self.load_font();
+        self.bus
+            .write_ram(&self.program, self.memory_map.entry_point);
+        self.cpu.reset(self.seed);
+    }
+
+    /// Like `reset`, but fills RAM first the way different interpreters
+    /// leave it at power-on
+    pub fn hard_reset(&mut self, fill: RamFill) {
+        match fill {
+            RamFill::Zero => self.bus.fill_ram(|| 0),
+            RamFill::Random => {
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                self.bus.fill_ram(|| rng.gen());
+            }
+        }
+        self.reset();
+    }
+
+    pub fn program(&self) -> &[u8] {
+        &self.program
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        self.bus.ram()
+    }
+
+    /// Writes to RAM like the program would, invalidating cached code
+    pub fn write_ram(&mut self, data: &[u8], address: u16) {
+        self.bus.write_ram(data, address);
+    }
+
+    /// Calls `callback` for every access of the kind in the address range.
+    /// The recompiler backend interprets while any hook is registered
+    pub fn add_memory_hook<F: FnMut(MemoryAccess) + 'static>(
+        &mut self,
+        kind: AccessKind,
+        range: RangeInclusive<u16>,
+        callback: F,
+    ) -> HookId {
+        self.bus.add_hook(kind, range, Box::new(callback))
+    }
+
+    pub fn remove_memory_hook(&mut self, id: HookId) -> bool {
+        self.bus.remove_hook(id)
+    }
+
+    pub fn rom_hash(&self) -> String {
+        sha1_smol::Sha1::from(&self.program).digest().to_string()
     }
 
     pub fn get_framebuffer(&self) -> &[u8] {
         self.bus.get_framebuffer()
     }
 
+    pub fn is_frame_dirty(&self) -> bool {
+        self.bus.is_framebuffer_dirty()
+    }
+
+    /// Returns the screen only if it changed since the previous call
+    pub fn take_frame_if_dirty(&mut self) -> Option<Frame<'_>> {
+        let dirty = self.bus.take_dirty()?;
+        Some(Frame {
+            pixels: self.bus.get_framebuffer(),
+            dirty,
+        })
+    }
+
     pub fn set_key_pressed(&mut self, key: Option<u8>) {
         self.bus.set_key_pressed(key);
     }
 
+    pub fn set_key_mask(&mut self, keys: u16) {
+        self.bus.set_key_mask(keys);
+    }
+
+    pub fn key_mask(&self) -> u16 {
+        self.bus.key_mask()
+    }
+
+    pub fn set_instruction_cache_enabled(&mut self, enabled: bool) {
+        self.bus.set_instruction_cache_enabled(enabled);
+    }
+
     pub fn run(&mut self) {
         self.cpu.run(&mut self.bus);
     }
 
+    pub fn run_cycles(&mut self, cycles: usize) {
+        match self.backend {
+            // Fetch hooks need every instruction to go through the bus
+            _ if self.bus.has_hooks() => {
+                for _ in 0..cycles {
+                    self.cpu.run(&mut self.bus);
+                }
+            }
+            Backend::Interpreter => self.cpu.run_cycles(&mut self.bus, cycles),
+            Backend::Recompiler => {
+                self.recompiler.run(&mut self.cpu, &mut self.bus, cycles);
+            }
+        }
+    }
+
+    pub fn run_frame(&mut self, cycles: usize) {
+        self.run_cycles(cycles);
+        self.tick_timers();
+    }
+
     pub fn tick_timers(&mut self) {
         self.cpu.tick_timers();
     }
@@ -75,6 +331,18 @@ impl Chip8 {
     pub fn sound_timer(&self) -> u8 {
         self.cpu.sound_timer()
     }
+
+    /// Set once the CPU has stopped on a reserved write, cleared by a reset
+    pub fn fault(&self) -> Option<Fault> {
+        self.cpu.fault()
+    }
+
+    fn load_font(&mut self) {
+        self.bus
+            .write_ram(self.font.small(), self.memory_map.font_address);
+        self.bus
+            .write_ram(self.font.big(), self.memory_map.big_font_address);
+    }
 }
 
 impl Default for Chip8 {
@@ -82,3 +350,67 @@ impl Default for Chip8 {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{play_pong, PONG};
+
+    #[test]
+    fn reset() {
+        let mut chip8 = Chip8::builder().seed(3).build();
+        chip8.set_program(PONG);
+        let first = play_pong(&mut chip8);
+
+        chip8.reset();
+        assert!(chip8.get_framebuffer().iter().all(|&pixel| pixel == 0));
+        assert_eq!(chip8.sound_timer(), 0);
+        assert_eq!(chip8.key_mask(), 0);
+        assert_eq!(play_pong(&mut chip8), first);
+    }
+
+    #[test]
+    fn hard_reset() {
+        let mut fresh = Chip8::builder().seed(3).build();
+        fresh.set_program(PONG);
+        let expected = play_pong(&mut fresh);
+
+        for fill in [RamFill::Zero, RamFill::Random] {
+            let mut chip8 = Chip8::builder().seed(3).build();
+            chip8.set_program(PONG);
+            play_pong(&mut chip8);
+
+            chip8.hard_reset(fill);
+            assert_eq!(play_pong(&mut chip8), expected);
+        }
+    }
+
+    #[test]
+    fn fault_halts_both_backends() {
+        let memory_map = MemoryMap {
+            reserved_writes: ReservedWrites::Fault,
+            ..MemoryMap::default()
+        };
+        for backend in [Backend::Interpreter, Backend::Recompiler] {
+            let mut chip8 = Chip8::builder()
+                .backend(backend)
+                .memory_map(memory_map)
+                .build();
+            chip8.set_program(&[
+                0xa0, 0x50, // 0x0200: ld I, 0x050
+                0xf0, 0x33, // 0x0202: ld B, v0
+            ]);
+            chip8.run_cycles(10);
+
+            let fault = Fault::ReservedWrite {
+                address: 0x050,
+                pc: 0x202,
+            };
+            assert_eq!(chip8.fault(), Some(fault));
+            assert_eq!(chip8.ram()[0x050], 0xf0);
+
+            chip8.reset();
+            assert_eq!(chip8.fault(), None);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use crate::{
+    font::{BIG_FONT_SIZE, SMALL_FONT_SIZE},
+    ram::RAM_SIZE,
+    ENTRY_POINT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReservedWrites {
+    Allow,
+    /// Print a warning and perform the write anyway
+    Log,
+    /// Skip the write and halt the CPU with a `Fault` until it's reset
+    Fault,
+}
+
+/// A region of the map that doesn't fit in RAM, with its address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryMapError {
+    Font(u16),
+    BigFont(u16),
+    EntryPoint(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryMap {
+    pub font_address: u16,
+    pub big_font_address: u16,
+    pub entry_point: u16,
+    pub reserved_writes: ReservedWrites,
+}
+
+impl MemoryMap {
+    /// The interpreter area below the entry point, and the fonts wherever
+    /// they're placed
+    pub fn is_reserved(&self, address: u16) -> bool {
+        let font =
+            |start: u16, size: usize| -> Range<usize> { start as usize..start as usize + size };
+        address < self.entry_point
+            || font(self.font_address, SMALL_FONT_SIZE).contains(&(address as usize))
+            || font(self.big_font_address, BIG_FONT_SIZE).contains(&(address as usize))
+    }
+
+    /// Both fonts have to fit in RAM, and the entry point has to leave room
+    /// for at least one instruction
+    pub fn validate(&self) -> Result<(), MemoryMapError> {
+        let fits = |start: u16, size: usize| start as usize + size <= RAM_SIZE;
+        if !fits(self.font_address, SMALL_FONT_SIZE) {
+            Err(MemoryMapError::Font(self.font_address))
+        } else if !fits(self.big_font_address, BIG_FONT_SIZE) {
+            Err(MemoryMapError::BigFont(self.big_font_address))
+        } else if !fits(self.entry_point, 2) {
+            Err(MemoryMapError::EntryPoint(self.entry_point))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self {
+            font_address: 0x050,
+            big_font_address: 0x0a0,
+            entry_point: ENTRY_POINT,
+            reserved_writes: ReservedWrites::Allow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8;
+
+    #[test]
+    fn font_and_entry_point() {
+        let memory_map = MemoryMap {
+            font_address: 0x100,
+            entry_point: 0x300,
+            ..MemoryMap::default()
+        };
+        let mut chip8 = Chip8::builder().memory_map(memory_map).build();
+        chip8.set_program(&[0x12, 0x34]);
+
+        assert_eq!(chip8.bus.read_ram(0x100), 0xf0);
+        assert_eq!(chip8.bus.read_ram(0x000), 0x00);
+        assert_eq!(chip8.bus.read_ram(0x300), 0x12);
+        assert_eq!(chip8.bus.read_ram(0x200), 0x00);
+        assert_eq!(chip8.cpu.pc(), 0x300);
+    }
+
+    #[test]
+    fn reserved_follows_the_map() {
+        let memory_map = MemoryMap {
+            font_address: 0x800,
+            entry_point: 0x300,
+            ..MemoryMap::default()
+        };
+
+        assert!(memory_map.is_reserved(0x2ff));
+        assert!(!memory_map.is_reserved(0x300));
+        assert!(memory_map.is_reserved(0x800));
+        assert!(memory_map.is_reserved(0x84f));
+        assert!(!memory_map.is_reserved(0x850));
+    }
+
+    #[test]
+    fn validate() {
+        let valid = MemoryMap {
+            font_address: 0xfb0,
+            big_font_address: 0xf10,
+            entry_point: 0xffe,
+            ..MemoryMap::default()
+        };
+        assert_eq!(valid.validate(), Ok(()));
+
+        let font = MemoryMap {
+            font_address: 0xff0,
+            ..MemoryMap::default()
+        };
+        assert_eq!(font.validate(), Err(MemoryMapError::Font(0xff0)));
+        let big_font = MemoryMap {
+            big_font_address: 0xf80,
+            ..MemoryMap::default()
+        };
+        assert_eq!(big_font.validate(), Err(MemoryMapError::BigFont(0xf80)));
+        let entry_point = MemoryMap {
+            entry_point: 0xfff,
+            ..MemoryMap::default()
+        };
+        assert_eq!(
+            entry_point.validate(),
+            Err(MemoryMapError::EntryPoint(0xfff))
+        );
+    }
+
+    #[test]
+    fn builder_replaces_an_invalid_map() {
+        let memory_map = MemoryMap {
+            font_address: 0xff0,
+            ..MemoryMap::default()
+        };
+        let chip8 = Chip8::builder().memory_map(memory_map).build();
+
+        assert_eq!(chip8.memory_map(), MemoryMap::default());
+        assert_eq!(chip8.bus.read_ram(0x050), 0xf0);
+    }
+
+    #[test]
+    fn long_program_is_cut_at_the_end_of_ram() {
+        let memory_map = MemoryMap {
+            entry_point: 0xffe,
+            ..MemoryMap::default()
+        };
+        let mut chip8 = Chip8::builder().memory_map(memory_map).build();
+        chip8.set_program(&[0x12, 0x34, 0x56, 0x78]);
+
+        assert_eq!(chip8.bus.read_ram(0xfff), 0x34);
+    }
+}
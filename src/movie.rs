@@ -0,0 +1,346 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    text_format::{parse_hex, to_hex, LineError, LineReader},
+    Chip8, Chip8Builder, Font, MemoryMap, Quirks, ReservedWrites,
+};
+
+const MAGIC: &str = "chip8-movie 1";
+// A day at 60 frames per second, well past any real recording
+const MAX_FRAMES: usize = 60 * 60 * 60 * 24;
+
+#[derive(Debug)]
+pub enum MovieError {
+    Io(io::Error),
+    Parse(usize),
+    UnexpectedEof,
+    /// The frames on this line take the movie past `MAX_FRAMES`
+    TooLong(usize),
+}
+
+impl From<io::Error> for MovieError {
+    fn from(error: io::Error) -> Self {
+        MovieError::Io(error)
+    }
+}
+
+impl From<LineError> for MovieError {
+    fn from(error: LineError) -> Self {
+        match error {
+            LineError::Io(error) => MovieError::Io(error),
+            LineError::UnexpectedEof => MovieError::UnexpectedEof,
+            LineError::Parse(line) => MovieError::Parse(line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movie {
+    pub rom_hash: String,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub font: Font,
+    pub memory_map: MemoryMap,
+    pub cycles_per_frame: usize,
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    pub fn new(chip8: &Chip8, cycles_per_frame: usize) -> Self {
+        Self {
+            rom_hash: chip8.rom_hash(),
+            seed: chip8.seed(),
+            quirks: chip8.quirks(),
+            font: chip8.font().clone(),
+            memory_map: chip8.memory_map(),
+            cycles_per_frame,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn chip8_builder(&self) -> Chip8Builder {
+        Chip8::builder()
+            .seed(self.seed)
+            .quirks(self.quirks)
+            .font(self.font.clone())
+            .memory_map(self.memory_map)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MovieError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MovieError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), MovieError> {
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(writer, "rom {}", self.rom_hash)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer, "cycles-per-frame {}", self.cycles_per_frame)?;
+        writeln!(
+            writer,
+            "quirks shift_uses_vy={} load_store_increments_i={} jump_uses_vx={} logic_resets_vf={}",
+            self.quirks.shift_uses_vy as u8,
+            self.quirks.load_store_increments_i as u8,
+            self.quirks.jump_uses_vx as u8,
+            self.quirks.logic_resets_vf as u8,
+        )?;
+        let font = [self.font.small(), self.font.big()].concat();
+        writeln!(writer, "font {}", to_hex(&font))?;
+        let reserved_writes = match self.memory_map.reserved_writes {
+            ReservedWrites::Allow => "allow",
+            ReservedWrites::Log => "log",
+            ReservedWrites::Fault => "fault",
+        };
+        writeln!(
+            writer,
+            "memory-map font={:03x} big-font={:03x} entry={:03x} reserved={}",
+            self.memory_map.font_address,
+            self.memory_map.big_font_address,
+            self.memory_map.entry_point,
+            reserved_writes,
+        )?;
+        writeln!(writer, "frames")?;
+
+        // Frames are run-length encoded as "<key mask> <count>"
+        let mut frames = self.frames.iter().peekable();
+        while let Some(&keys) = frames.next() {
+            let mut count = 1;
+            while frames.next_if_eq(&&keys).is_some() {
+                count += 1;
+            }
+            writeln!(writer, "{:04x} {}", keys, count)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, MovieError> {
+        let mut lines = LineReader::new(reader);
+
+        lines.expect(MAGIC)?;
+        let (_, rom_hash) = lines.expect("rom")?;
+        let (line, seed) = lines.expect("seed")?;
+        let seed = seed.parse().map_err(|_| MovieError::Parse(line))?;
+        let (line, cycles_per_frame) = lines.expect("cycles-per-frame")?;
+        let cycles_per_frame = cycles_per_frame
+            .parse()
+            .map_err(|_| MovieError::Parse(line))?;
+        let (line, quirks) = lines.expect("quirks")?;
+        let quirks = Self::parse_quirks(&quirks).ok_or(MovieError::Parse(line))?;
+
+        // Movies from before the font and memory map were recorded go
+        // straight to the frames
+        let mut font = Font::default();
+        let mut memory_map = MemoryMap::default();
+        loop {
+            let (number, line) = lines.next_line()?;
+            if line == "frames" {
+                break;
+            } else if let Some(hex) = line.strip_prefix("font ") {
+                font = Self::parse_font(hex).ok_or(MovieError::Parse(number))?;
+            } else if let Some(map) = line.strip_prefix("memory-map ") {
+                memory_map = Self::parse_memory_map(map).ok_or(MovieError::Parse(number))?;
+            } else {
+                return Err(MovieError::Parse(number));
+            }
+        }
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let (number, line) = line?;
+            let mut parts = line.split_whitespace();
+            let keys = parts
+                .next()
+                .and_then(|keys| u16::from_str_radix(keys, 16).ok());
+            let count = parts.next().and_then(|count| count.parse::<usize>().ok());
+
+            match (keys, count) {
+                (Some(_), Some(count)) if count > MAX_FRAMES - frames.len() => {
+                    return Err(MovieError::TooLong(number))
+                }
+                (Some(keys), Some(count)) => frames.resize(frames.len() + count, keys),
+                _ => return Err(MovieError::Parse(number)),
+            }
+        }
+
+        Ok(Self {
+            rom_hash,
+            seed,
+            quirks,
+            font,
+            memory_map,
+            cycles_per_frame,
+            frames,
+        })
+    }
+
+    fn parse_font(hex: &str) -> Option<Font> {
+        Font::from_bytes(&parse_hex(hex)?).ok()
+    }
+
+    fn parse_memory_map(line: &str) -> Option<MemoryMap> {
+        let mut memory_map = MemoryMap::default();
+
+        for pair in line.split_whitespace() {
+            let (key, value) = pair.split_once('=')?;
+            match key {
+                "font" => memory_map.font_address = u16::from_str_radix(value, 16).ok()?,
+                "big-font" => memory_map.big_font_address = u16::from_str_radix(value, 16).ok()?,
+                "entry" => memory_map.entry_point = u16::from_str_radix(value, 16).ok()?,
+                "reserved" => {
+                    memory_map.reserved_writes = match value {
+                        "allow" => ReservedWrites::Allow,
+                        "log" => ReservedWrites::Log,
+                        "fault" => ReservedWrites::Fault,
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        memory_map.validate().ok()?;
+        Some(memory_map)
+    }
+
+    fn parse_quirks(line: &str) -> Option<Quirks> {
+        let mut quirks = Quirks::default();
+
+        for pair in line.split_whitespace() {
+            let (key, value) = pair.split_once('=')?;
+            let value = match value {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            };
+
+            match key {
+                "shift_uses_vy" => quirks.shift_uses_vy = value,
+                "load_store_increments_i" => quirks.load_store_increments_i = value,
+                "jump_uses_vx" => quirks.jump_uses_vx = value,
+                "logic_resets_vf" => quirks.logic_resets_vf = value,
+                _ => return None,
+            }
+        }
+
+        Some(quirks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{paddle, play, PONG};
+
+    fn replay(movie: &Movie) -> Vec<u8> {
+        let mut chip8 = movie.chip8_builder().build();
+        chip8.set_program(PONG);
+        play(&mut chip8, &movie.frames, movie.cycles_per_frame)
+    }
+
+    #[test]
+    fn write_read() {
+        let mut chip8 = Chip8::builder().seed(42).build();
+        chip8.set_program(PONG);
+        let mut movie = Movie::new(&chip8, 8);
+        movie.quirks.shift_uses_vy = true;
+        movie.font = Font::schip();
+        movie.memory_map.entry_point = 0x300;
+        movie.memory_map.reserved_writes = ReservedWrites::Fault;
+        movie.frames = vec![0, 0, 0, 0x0010, 0x0010, 0, 0x8001];
+
+        let mut buffer = Vec::new();
+        movie.write(&mut buffer).unwrap();
+        let actual = Movie::read(buffer.as_slice()).unwrap();
+
+        assert_eq!(actual, movie);
+    }
+
+    #[test]
+    fn builder_restores_font_and_memory_map() {
+        let memory_map = MemoryMap {
+            font_address: 0x100,
+            ..MemoryMap::default()
+        };
+        let chip8 = Chip8::builder()
+            .font(Font::fish_n_chips())
+            .memory_map(memory_map)
+            .build();
+        let movie = Movie::new(&chip8, 8);
+
+        let mut buffer = Vec::new();
+        movie.write(&mut buffer).unwrap();
+        let replayed = Movie::read(buffer.as_slice())
+            .unwrap()
+            .chip8_builder()
+            .build();
+
+        assert_eq!(replayed.font(), &Font::fish_n_chips());
+        assert_eq!(replayed.memory_map(), memory_map);
+    }
+
+    #[test]
+    fn reads_movies_without_font() {
+        let text = "chip8-movie 1\nrom abc\nseed 1\ncycles-per-frame 8\nquirks \nframes\n0000 2\n";
+        let movie = Movie::read(text.as_bytes()).unwrap();
+
+        assert_eq!(movie.font, Font::default());
+        assert_eq!(movie.memory_map, MemoryMap::default());
+        assert_eq!(movie.frames, [0, 0]);
+    }
+
+    #[test]
+    fn parse_error() {
+        let text = "chip8-movie 1\nrom abc\nseed nope\n";
+        let result = Movie::read(text.as_bytes());
+
+        assert!(matches!(result, Err(MovieError::Parse(3))));
+    }
+
+    #[test]
+    fn memory_map_out_of_range() {
+        let header = "chip8-movie 1\nrom abc\nseed 1\ncycles-per-frame 8\nquirks \n";
+        let text = format!("{}memory-map font=ff0\nframes\n", header);
+        let result = Movie::read(text.as_bytes());
+
+        assert!(matches!(result, Err(MovieError::Parse(6))));
+    }
+
+    #[test]
+    fn truncated() {
+        let text = "chip8-movie 1\nrom abc\n";
+        let result = Movie::read(text.as_bytes());
+
+        assert!(matches!(result, Err(MovieError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn too_long() {
+        let header = "chip8-movie 1\nrom abc\nseed 1\ncycles-per-frame 8\nquirks \nframes\n";
+        let text = format!("{}0000 {}\n0001 1\n", header, MAX_FRAMES);
+        let result = Movie::read(text.as_bytes());
+        assert!(matches!(result, Err(MovieError::TooLong(8))));
+
+        let text = format!("{}0000 {}\n", header, usize::MAX);
+        let result = Movie::read(text.as_bytes());
+        assert!(matches!(result, Err(MovieError::TooLong(7))));
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let mut chip8 = Chip8::builder().seed(7).build();
+        chip8.set_program(PONG);
+        let mut movie = Movie::new(&chip8, 8);
+        movie.frames = paddle(600);
+
+        assert_eq!(replay(&movie), replay(&movie));
+    }
+}
@@ -0,0 +1,269 @@
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+const MARGIN: usize = 2;
+const MAX_MESSAGES: usize = 4;
+const TEXT_COLOR: u32 = 0xffffffff;
+
+struct Message {
+    text: String,
+    frames_left: u32,
+}
+
+/// On-screen display drawn over the emulated screen: transient messages in
+/// the bottom left, a stats line in the top left and a centred banner
+pub struct Osd {
+    scale: usize,
+    messages: Vec<Message>,
+    stats: Option<String>,
+    banner: Option<String>,
+}
+
+impl Osd {
+    pub fn new(scale: usize) -> Self {
+        Self {
+            scale,
+            messages: Vec::new(),
+            stats: None,
+            banner: None,
+        }
+    }
+
+    /// Shows a message for the given number of frames
+    pub fn show_message<S: Into<String>>(&mut self, text: S, frames: u32) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(Message {
+            text: text.into(),
+            frames_left: frames,
+        });
+    }
+
+    pub fn set_stats(&mut self, stats: Option<String>) {
+        self.stats = stats;
+    }
+
+    pub fn set_banner(&mut self, banner: Option<String>) {
+        self.banner = banner;
+    }
+
+    /// Counts down the messages' lifetimes, call once per displayed frame
+    pub fn tick(&mut self) {
+        for message in &mut self.messages {
+            message.frames_left = message.frames_left.saturating_sub(1);
+        }
+        self.messages.retain(|message| message.frames_left > 0);
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.messages.is_empty() || self.stats.is_some() || self.banner.is_some()
+    }
+
+    pub fn draw(&self, buffer: &mut [u32], width: usize, height: usize) {
+        let scale = self.scale;
+        let line_height = line_height(scale);
+
+        if let Some(stats) = &self.stats {
+            draw_text(buffer, width, height, stats, 0, 0, scale);
+        }
+
+        if let Some(banner) = &self.banner {
+            let x = width.saturating_sub(text_width(banner, scale)) / 2;
+            let y = height.saturating_sub(line_height) / 2;
+            draw_text(buffer, width, height, banner, x, y, scale);
+        }
+
+        let mut y = height;
+        for message in self.messages.iter().rev() {
+            y = y.saturating_sub(line_height);
+            draw_text(buffer, width, height, &message.text, 0, y, scale);
+        }
+    }
+}
+
+/// Height of a line of text including its box
+pub(crate) fn line_height(scale: usize) -> usize {
+    (GLYPH_HEIGHT + MARGIN * 2) * scale
+}
+
+pub(crate) fn text_width(text: &str, scale: usize) -> usize {
+    let advance = GLYPH_WIDTH + 1;
+    let glyphs = (text.chars().count() * advance).saturating_sub(1);
+    (glyphs + MARGIN * 2) * scale
+}
+
+/// Draws text on a darkened box with its top left corner at x, y
+pub(crate) fn draw_text(
+    buffer: &mut [u32],
+    width: usize,
+    height: usize,
+    text: &str,
+    x: usize,
+    y: usize,
+    scale: usize,
+) {
+    let box_width = text_width(text, scale);
+    for py in y..(y + line_height(scale)).min(height) {
+        for px in x..(x + box_width).min(width) {
+            let pixel = &mut buffer[py * width + px];
+            *pixel = darken(*pixel);
+        }
+    }
+
+    for (index, c) in text.chars().enumerate() {
+        let glyph = glyph(c);
+        let glyph_x = x + (MARGIN + index * (GLYPH_WIDTH + 1)) * scale;
+        let glyph_y = y + MARGIN * scale;
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                let left = glyph_x + column * scale;
+                let top = glyph_y + row * scale;
+                for py in top..(top + scale).min(height) {
+                    for px in left..(left + scale).min(width) {
+                        buffer[py * width + px] = TEXT_COLOR;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A quarter of the brightness
+fn darken(color: u32) -> u32 {
+    (color & 0xff000000) | ((color >> 2) & 0x003f3f3f)
+}
+
+/// 3x5 glyphs, one row per byte with the leftmost pixel in bit 2. Lower case
+/// letters use the upper case glyphs
+#[rustfmt::skip]
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K: u32 = 0xff000000;
+    const W: u32 = TEXT_COLOR;
+
+    #[test]
+    fn draw_glyph() {
+        let mut osd = Osd::new(1);
+        osd.set_stats(Some("T".to_string()));
+
+        let (width, height) = (8, 10);
+        let mut buffer = vec![K; width * height];
+        osd.draw(&mut buffer, width, height);
+
+        #[rustfmt::skip]
+        let expected = [
+            K, K, K, K, K, K, K, K,
+            K, K, K, K, K, K, K, K,
+            K, K, W, W, W, K, K, K,
+            K, K, K, W, K, K, K, K,
+            K, K, K, W, K, K, K, K,
+            K, K, K, W, K, K, K, K,
+            K, K, K, W, K, K, K, K,
+            K, K, K, K, K, K, K, K,
+            K, K, K, K, K, K, K, K,
+            K, K, K, K, K, K, K, K,
+        ];
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn background_is_darkened() {
+        let mut osd = Osd::new(2);
+        osd.set_banner(Some("PAUSED".to_string()));
+
+        let (width, height) = (64, 32);
+        let mut buffer = vec![0xff808080; width * height];
+        osd.draw(&mut buffer, width, height);
+
+        assert_eq!(buffer[0], 0xff808080);
+        assert!(buffer.contains(&0xff202020));
+        assert!(buffer.contains(&W));
+    }
+
+    #[test]
+    fn messages_expire() {
+        let mut osd = Osd::new(1);
+        assert!(!osd.is_visible());
+
+        osd.show_message("Saved", 2);
+        assert!(osd.is_visible());
+        osd.tick();
+        assert!(osd.is_visible());
+        osd.tick();
+        assert!(!osd.is_visible());
+    }
+
+    #[test]
+    fn oldest_message_is_dropped() {
+        let mut osd = Osd::new(1);
+        for i in 0..MAX_MESSAGES + 1 {
+            osd.show_message(i.to_string(), 10);
+        }
+
+        assert_eq!(osd.messages.len(), MAX_MESSAGES);
+        assert_eq!(osd.messages[0].text, "1");
+    }
+}
@@ -0,0 +1,111 @@
+/// Colours for each pixel value, as 0xAARRGGBB. CHIP-8 and SCHIP only use the
+/// first two, XO-CHIP uses all four for its two bitplanes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub name: &'static str,
+    pub colors: [u32; 4],
+}
+
+impl Palette {
+    pub const CLASSIC: Palette = Palette {
+        name: "classic",
+        colors: [0xff000000, 0xffffffff, 0xffaaaaaa, 0xff555555],
+    };
+    pub const AMBER: Palette = Palette {
+        name: "amber",
+        colors: [0xff1a0f00, 0xffffb000, 0xffcc7700, 0xff663300],
+    };
+    pub const GREEN_PHOSPHOR: Palette = Palette {
+        name: "green",
+        colors: [0xff001a00, 0xff33ff33, 0xff22aa22, 0xff115511],
+    };
+    pub const OCTO: Palette = Palette {
+        name: "octo",
+        colors: [0xff996600, 0xffffcc00, 0xffff6600, 0xff662200],
+    };
+    pub const LCD: Palette = Palette {
+        name: "lcd",
+        colors: [0xfff9ffb3, 0xff3d8026, 0xffabcc47, 0xff00131a],
+    };
+
+    pub const PRESETS: [Palette; 5] = [
+        Self::CLASSIC,
+        Self::AMBER,
+        Self::GREEN_PHOSPHOR,
+        Self::OCTO,
+        Self::LCD,
+    ];
+
+    pub fn preset(name: &str) -> Option<Palette> {
+        Self::PRESETS
+            .iter()
+            .find(|palette| palette.name == name)
+            .copied()
+    }
+
+    /// The preset after this one, wrapping around. Custom palettes go back to
+    /// the first preset
+    pub fn next_preset(&self) -> Palette {
+        let index = Self::PRESETS
+            .iter()
+            .position(|palette| palette == self)
+            .map_or(0, |index| (index + 1) % Self::PRESETS.len());
+        Self::PRESETS[index]
+    }
+
+    pub fn background(&self) -> u32 {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> u32 {
+        self.colors[1]
+    }
+
+    pub fn color(&self, pixel: u8) -> u32 {
+        self.colors[(pixel & 0x3) as usize]
+    }
+
+    pub fn render(&self, pixels: &[u8]) -> Vec<u32> {
+        pixels.iter().map(|&pixel| self.color(pixel)).collect()
+    }
+
+    pub fn render_into(&self, pixels: &[u8], buffer: &mut [u32]) {
+        for (color, &pixel) in buffer.iter_mut().zip(pixels) {
+            *color = self.color(pixel);
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let palette = Palette::OCTO;
+
+        let actual = palette.render(&[0, 1, 2, 3]);
+        assert_eq!(actual, palette.colors);
+    }
+
+    #[test]
+    fn presets() {
+        assert_eq!(Palette::preset("lcd"), Some(Palette::LCD));
+        assert_eq!(Palette::preset("nope"), None);
+
+        assert_eq!(Palette::CLASSIC.next_preset(), Palette::AMBER);
+        assert_eq!(Palette::LCD.next_preset(), Palette::CLASSIC);
+
+        let custom = Palette {
+            name: "custom",
+            colors: [0; 4],
+        };
+        assert_eq!(custom.next_preset(), Palette::CLASSIC);
+    }
+}
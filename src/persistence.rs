@@ -0,0 +1,150 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Persistence {
+    /// Unlit pixels lose this fraction of their brightness every frame
+    Decay(f32),
+    /// Every pixel shows the average of the current and the previous frame
+    Blend,
+}
+
+pub struct PhosphorFilter {
+    mode: Persistence,
+    on_color: u32,
+    off_color: u32,
+    intensity: Vec<f32>,
+    previous: Vec<u8>,
+    output: Vec<u32>,
+}
+
+impl PhosphorFilter {
+    pub fn new(mode: Persistence, on_color: u32, off_color: u32) -> Self {
+        Self {
+            mode,
+            on_color,
+            off_color,
+            intensity: Vec::new(),
+            previous: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> Persistence {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Persistence) {
+        self.mode = mode;
+    }
+
+    /// Also recolours the current output, so it can be shown while paused
+    pub fn set_colors(&mut self, on_color: u32, off_color: u32) {
+        self.on_color = on_color;
+        self.off_color = off_color;
+        for (output, &intensity) in self.output.iter_mut().zip(&self.intensity) {
+            *output = mix(off_color, on_color, intensity);
+        }
+    }
+
+    /// The last processed frame, empty until the first one
+    pub fn output(&self) -> &[u32] {
+        &self.output
+    }
+
+    /// Feeds the next frame through the filter. It has to be called once per
+    /// emulated frame, even when the screen didn't change, for the decay to run
+    pub fn process(&mut self, pixels: &[u8]) -> &[u32] {
+        if self.intensity.len() != pixels.len() {
+            self.intensity = pixels.iter().map(|&pixel| pixel as f32).collect();
+            self.previous = pixels.to_vec();
+            self.output = vec![self.off_color; pixels.len()];
+        }
+
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let lit = if pixel > 0 { 1.0 } else { 0.0 };
+
+            let intensity = match self.mode {
+                Persistence::Decay(decay) => {
+                    let faded = self.intensity[i] * (1.0 - decay.clamp(0.0, 1.0));
+                    let faded = if faded < 1.0 / 255.0 { 0.0 } else { faded };
+                    faded.max(lit)
+                }
+                Persistence::Blend => {
+                    let previous = if self.previous[i] > 0 { 1.0 } else { 0.0 };
+                    (lit + previous) / 2.0
+                }
+            };
+
+            self.intensity[i] = intensity;
+            self.output[i] = mix(self.off_color, self.on_color, intensity);
+        }
+        self.previous.copy_from_slice(pixels);
+
+        &self.output
+    }
+}
+
+fn mix(from: u32, to: u32, amount: f32) -> u32 {
+    let channel = |shift: u32| {
+        let from = ((from >> shift) & 0xff) as f32;
+        let to = ((to >> shift) & 0xff) as f32;
+        let value = (from + (to - from) * amount).round() as u32;
+        value << shift
+    };
+
+    0xff000000 | channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ON: u32 = 0xffffffff;
+    const OFF: u32 = 0xff000000;
+
+    #[test]
+    fn decay() {
+        let mut filter = PhosphorFilter::new(Persistence::Decay(0.5), ON, OFF);
+
+        assert_eq!(filter.process(&[1, 0]), &[ON, OFF]);
+        assert_eq!(filter.process(&[0, 0]), &[0xff808080, OFF]);
+        assert_eq!(filter.process(&[0, 1]), &[0xff404040, ON]);
+        assert_eq!(filter.process(&[1, 1]), &[ON, ON]);
+    }
+
+    #[test]
+    fn decay_settles() {
+        let mut filter = PhosphorFilter::new(Persistence::Decay(0.5), ON, OFF);
+        filter.process(&[1]);
+
+        for _ in 0..8 {
+            filter.process(&[0]);
+        }
+        assert_eq!(filter.process(&[0]), &[OFF]);
+    }
+
+    #[test]
+    fn recolor_keeps_intensity() {
+        let mut filter = PhosphorFilter::new(Persistence::Decay(0.5), ON, OFF);
+        filter.process(&[1, 0]);
+        filter.process(&[0, 0]);
+
+        filter.set_colors(0xff00ff00, OFF);
+        assert_eq!(filter.output(), &[0xff008000, OFF]);
+    }
+
+    #[test]
+    fn blend() {
+        let mut filter = PhosphorFilter::new(Persistence::Blend, ON, OFF);
+
+        assert_eq!(filter.process(&[1, 0]), &[ON, OFF]);
+        assert_eq!(filter.process(&[0, 1]), &[0xff808080, 0xff808080]);
+        assert_eq!(filter.process(&[0, 1]), &[OFF, ON]);
+    }
+
+    #[test]
+    fn colors() {
+        let mut filter = PhosphorFilter::new(Persistence::Blend, 0xff00ff00, 0xff000040);
+
+        filter.process(&[1]);
+        assert_eq!(filter.process(&[0]), &[0xff008020]);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write, rc::Rc};
+
+use crate::{
+    ram::RAM_SIZE, stack::STACK_SIZE, AccessKind, Chip8, HookId, Instruction, MemoryAccess,
+};
+
+const HOT_SPOTS: usize = 10;
+const BAR_WIDTH: u64 = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunctionStats {
+    pub calls: u64,
+    /// Instructions run in the function itself
+    pub self_instructions: u64,
+    /// Instructions run in the function and everything it called
+    pub inclusive_instructions: u64,
+}
+
+/// What ran and how often. Functions are keyed by their entry address, and
+/// the program's entry point stands for the code outside any subroutine
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub executions: Vec<u64>,
+    pub classes: BTreeMap<&'static str, u64>,
+    pub functions: BTreeMap<u16, FunctionStats>,
+    pub instructions: u64,
+    entry_point: u16,
+    // Mirrors the CPU's stack, with the function entered at each level
+    stack: Vec<u16>,
+}
+
+impl Profile {
+    fn new(entry_point: u16) -> Self {
+        Self {
+            executions: vec![0; RAM_SIZE],
+            classes: BTreeMap::new(),
+            functions: BTreeMap::new(),
+            instructions: 0,
+            entry_point,
+            stack: vec![entry_point],
+        }
+    }
+
+    fn record(&mut self, address: u16, opcode: u16) {
+        self.executions[address as usize % RAM_SIZE] += 1;
+        self.instructions += 1;
+
+        let instruction = Instruction::decode(opcode).ok();
+        if let Some(instruction) = instruction {
+            *self.classes.entry(instruction.pattern()).or_default() += 1;
+        }
+
+        let current = *self.stack.last().unwrap();
+        self.functions.entry(current).or_default().self_instructions += 1;
+        // Recursive functions count once per instruction
+        for (depth, &function) in self.stack.iter().enumerate() {
+            if !self.stack[..depth].contains(&function) {
+                self.functions
+                    .entry(function)
+                    .or_default()
+                    .inclusive_instructions += 1;
+            }
+        }
+
+        match instruction {
+            Some(Instruction::Call(nnn)) if self.stack.len() <= STACK_SIZE => {
+                self.functions.entry(nnn).or_default().calls += 1;
+                self.stack.push(nnn);
+            }
+            Some(Instruction::Ret) if self.stack.len() > 1 => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Hot spots, opcode classes and functions as text tables
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let percent = |count: u64| 100.0 * count as f64 / self.instructions.max(1) as f64;
+
+        writeln!(report, "{} instructions", self.instructions).unwrap();
+
+        writeln!(report, "\nHot spots\naddress      count       %").unwrap();
+        let mut addresses: Vec<usize> = (0..RAM_SIZE).filter(|&a| self.executions[a] > 0).collect();
+        addresses.sort_by_key(|&address| std::cmp::Reverse(self.executions[address]));
+        for &address in addresses.iter().take(HOT_SPOTS) {
+            let count = self.executions[address];
+            writeln!(
+                report,
+                "{:#06x} {:>11} {:>6.2}",
+                address,
+                count,
+                percent(count)
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\nOpcodes\nclass        count       %").unwrap();
+        let mut classes: Vec<_> = self.classes.iter().collect();
+        classes.sort_by_key(|&(_, &count)| std::cmp::Reverse(count));
+        for (class, &count) in classes {
+            writeln!(report, "{:<6} {:>11} {:>6.2}", class, count, percent(count)).unwrap();
+        }
+
+        writeln!(
+            report,
+            "\nFunctions\nfunction     calls        self   inclusive"
+        )
+        .unwrap();
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by_key(|&(_, stats)| std::cmp::Reverse(stats.inclusive_instructions));
+        for (&function, stats) in functions {
+            let name = if function == self.entry_point {
+                "main".to_string()
+            } else {
+                format!("{:#06x}", function)
+            };
+            writeln!(
+                report,
+                "{:<6} {:>11} {:>11} {:>11}",
+                name, stats.calls, stats.self_instructions, stats.inclusive_instructions
+            )
+            .unwrap();
+        }
+
+        report
+    }
+
+    /// A disassembly of `code`, loaded at `start`, with each instruction's
+    /// execution count and a bar relative to the hottest one
+    pub fn heatmap(&self, code: &[u8], start: u16) -> String {
+        let end = start as usize + code.len();
+        let max = self.executions[start as usize..end.min(RAM_SIZE)]
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let mut heatmap = String::new();
+        let mut address = start as usize;
+        while address + 1 < end {
+            // Code that runs at odd addresses lines up with the data around it
+            if self.executions[address] == 0 && self.executions[address + 1] > 0 {
+                address += 1;
+            }
+            let offset = address - start as usize;
+            let opcode = u16::from_be_bytes([code[offset], *code.get(offset + 1).unwrap_or(&0)]);
+            let text = match Instruction::decode(opcode) {
+                Ok(instruction) => instruction.to_string(),
+                Err(_) => format!("DW {:#06x}", opcode),
+            };
+
+            let count = self.executions[address];
+            let bar = "#".repeat(((count * BAR_WIDTH).div_ceil(max)) as usize);
+            writeln!(
+                heatmap,
+                "{:#06x}  {:04x}  {:<20} {:>11}  {}",
+                address, opcode, text, count, bar
+            )
+            .unwrap();
+            address += 2;
+        }
+        heatmap
+    }
+}
+
+/// Collects a `Profile` through a fetch hook while attached
+pub struct Profiler {
+    profile: Rc<RefCell<Profile>>,
+    hook: HookId,
+}
+
+impl Profiler {
+    pub fn attach(chip8: &mut Chip8) -> Self {
+        let profile = Rc::new(RefCell::new(Profile::new(chip8.memory_map().entry_point)));
+        let sink = Rc::clone(&profile);
+        let hook =
+            chip8.add_memory_hook(AccessKind::Fetch, 0..=0xfff, move |access: MemoryAccess| {
+                sink.borrow_mut().record(access.address, access.value)
+            });
+        Self { profile, hook }
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile.borrow().clone()
+    }
+
+    pub fn detach(self, chip8: &mut Chip8) -> Profile {
+        chip8.remove_memory_hook(self.hook);
+        self.profile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // main: call 0x206, jump to itself
+    // 0x206: call 0x20a, return
+    // 0x20a: V0 += 1, return
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 14] = [
+        0x22, 0x06, 0x12, 0x02, 0x00, 0x00,
+        0x22, 0x0a, 0x00, 0xee,
+        0x70, 0x01, 0x00, 0xee,
+    ];
+
+    fn profile(cycles: usize) -> Profile {
+        let mut chip8 = Chip8::new();
+        chip8.set_program(&PROGRAM);
+        let profiler = Profiler::attach(&mut chip8);
+        chip8.run_cycles(cycles);
+        profiler.detach(&mut chip8)
+    }
+
+    #[test]
+    fn counts_functions() {
+        let profile = profile(10);
+
+        assert_eq!(profile.instructions, 10);
+        assert_eq!(profile.executions[0x202], 5);
+        assert_eq!(profile.classes["2nnn"], 2);
+        assert_eq!(profile.classes["00EE"], 2);
+        assert_eq!(
+            profile.functions[&0x200],
+            FunctionStats {
+                calls: 0,
+                self_instructions: 6,
+                inclusive_instructions: 10,
+            }
+        );
+        assert_eq!(
+            profile.functions[&0x206],
+            FunctionStats {
+                calls: 1,
+                self_instructions: 2,
+                inclusive_instructions: 4,
+            }
+        );
+        assert_eq!(
+            profile.functions[&0x20a],
+            FunctionStats {
+                calls: 1,
+                self_instructions: 2,
+                inclusive_instructions: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn heatmap() {
+        let heatmap = profile(10).heatmap(&PROGRAM, 0x200);
+        let lines: Vec<&str> = heatmap.lines().collect();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(
+            lines[1],
+            format!(
+                "0x0202  1202  {:<20} {:>11}  {}",
+                "JP 0x202",
+                5,
+                "#".repeat(20)
+            )
+        );
+        assert!(lines[2].starts_with("0x0204  0000  DW 0x0000"));
+        assert!(lines[5].ends_with(&format!("{:>11}  {}", 1, "#".repeat(4))));
+    }
+
+    #[test]
+    fn report_lists_main() {
+        let report = profile(10).report();
+        assert!(report.starts_with("10 instructions\n"));
+        assert!(report.contains("\nmain             0           6          10\n"));
+    }
+}
@@ -0,0 +1,22 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift vy into vx instead of shifting vx in place
+    pub shift_uses_vy: bool,
+    /// Fx55/Fx65 leave I pointing past the last register
+    pub load_store_increments_i: bool,
+    /// Bnnn jumps to nnn + vx, where x is the high nibble of nnn
+    pub jump_uses_vx: bool,
+    /// 8xy1/8xy2/8xy3 reset vf to zero
+    pub logic_resets_vf: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            shift_uses_vy: false,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+        }
+    }
+}
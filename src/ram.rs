@@ -18,7 +18,7 @@ impl Ram {
     }
 
     pub fn write(&mut self, data: &[u8], address: usize) -> Result<(), RamError> {
-        if address + data.len() >= RAM_SIZE {
+        if address + data.len() > RAM_SIZE {
             return Err(RamError::BadWriteAddress);
         }
 
@@ -29,6 +29,16 @@ impl Ram {
         Ok(())
     }
 
+    pub fn fill_with<F: FnMut() -> u8>(&mut self, mut f: F) {
+        for byte in self.memory.iter_mut() {
+            *byte = f();
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.memory
+    }
+
     pub fn read(&self, address: usize) -> Result<u8, RamError> {
         if address >= RAM_SIZE {
             return Err(RamError::BadReadAddress);
@@ -50,6 +60,14 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn write_bounds() {
+        let mut ram = Ram::new();
+
+        assert!(ram.write(&[0xff], RAM_SIZE - 1).is_ok());
+        assert!(ram.write(&[0xff, 0xfe], RAM_SIZE - 1).is_err());
+    }
+
     #[test]
     fn read_byte() {
         let mut ram = Ram::new();
@@ -62,4 +80,13 @@ mod tests {
         assert_eq!(actual_0, 0xff);
         assert_eq!(actual_1, 0xfe);
     }
+
+    #[test]
+    fn fill_with() {
+        let mut ram = Ram::new();
+        ram.fill_with(|| 0xaa);
+
+        assert_eq!(ram.read(0).unwrap(), 0xaa);
+        assert_eq!(ram.read(RAM_SIZE - 1).unwrap(), 0xaa);
+    }
 }
@@ -0,0 +1,398 @@
+use std::ops::Range;
+
+use crate::{bus::Bus, cpu::Cpu, instruction::Instruction, quirks::Quirks, ram::RAM_SIZE};
+
+const MAX_BLOCK_LEN: usize = 64;
+
+type Op = Box<dyn Fn(&mut Cpu, &mut Bus)>;
+
+struct Block {
+    ops: Vec<Op>,
+    // Instructions covered, including the jumps folded into the block
+    len: usize,
+    end: u16,
+    // The code the block was compiled from, split at each folded jump
+    segments: Vec<Range<u16>>,
+    writes_ram: bool,
+}
+
+impl Block {
+    /// Compiles up to the next instruction that needs the CPU state to pick
+    /// where to go, following unconditional jumps along the way
+    fn compile(bus: &mut Bus, start: u16, quirks: Quirks) -> Option<Self> {
+        let mut ops = Vec::new();
+        let mut len = 0;
+        let mut segments = Vec::new();
+        let mut segment_start = start;
+        let mut pc = start;
+        let mut writes_ram = false;
+
+        while len < MAX_BLOCK_LEN {
+            let instruction = match bus.fetch_instruction(pc) {
+                Ok(instruction) => instruction,
+                Err(_) => break,
+            };
+            len += 1;
+
+            if let Instruction::Jp(nnn) = instruction {
+                segments.push(segment_start..pc.wrapping_add(2));
+                segment_start = nnn;
+                pc = nnn;
+                // A loop goes back to code the block already has
+                if segments.iter().any(|segment| segment.contains(&nnn)) {
+                    return Some(Self {
+                        ops,
+                        len,
+                        end: pc,
+                        segments,
+                        writes_ram,
+                    });
+                }
+                continue;
+            }
+
+            pc = pc.wrapping_add(2);
+            ops.push(Self::compile_op(instruction, quirks));
+
+            if Self::ends_block(instruction) {
+                writes_ram = matches!(
+                    instruction,
+                    Instruction::LdBVx { .. } | Instruction::LdIVx { .. }
+                );
+                break;
+            }
+        }
+        segments.push(segment_start..pc);
+
+        if len == 0 {
+            None
+        } else {
+            Some(Self {
+                ops,
+                len,
+                end: pc,
+                segments,
+                writes_ram,
+            })
+        }
+    }
+
+    fn overlaps(&self, written: &Range<u16>) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| segment.start < written.end && written.start < segment.end)
+    }
+
+    /// Binds operands and quirks into a closure for the common instructions,
+    /// the rest go through the interpreter's `execute`
+    fn compile_op(instruction: Instruction, quirks: Quirks) -> Op {
+        match instruction {
+            Instruction::SeVxByte { x, kk } => {
+                Box::new(move |cpu, _| skip_if(cpu, cpu.read_reg(x) == kk))
+            }
+            Instruction::SneVxByte { x, kk } => {
+                Box::new(move |cpu, _| skip_if(cpu, cpu.read_reg(x) != kk))
+            }
+            Instruction::SeVxVy { x, y } => {
+                Box::new(move |cpu, _| skip_if(cpu, cpu.read_reg(x) == cpu.read_reg(y)))
+            }
+            Instruction::SneVxVy { x, y } => {
+                Box::new(move |cpu, _| skip_if(cpu, cpu.read_reg(x) != cpu.read_reg(y)))
+            }
+            Instruction::Skp { x } => {
+                Box::new(move |cpu, bus| skip_if(cpu, bus.is_key_pressed(cpu.read_reg(x))))
+            }
+            Instruction::Sknp { x } => {
+                Box::new(move |cpu, bus| skip_if(cpu, !bus.is_key_pressed(cpu.read_reg(x))))
+            }
+            Instruction::LdVxByte { x, kk } => Box::new(move |cpu, _| cpu.write_reg(x, kk)),
+            Instruction::AddVxByte { x, kk } => {
+                Box::new(move |cpu, _| cpu.write_reg(x, cpu.read_reg(x).wrapping_add(kk)))
+            }
+            Instruction::LdVxVy { x, y } => {
+                Box::new(move |cpu, _| cpu.write_reg(x, cpu.read_reg(y)))
+            }
+            Instruction::Or { x, y } => logic(x, y, quirks, |a, b| a | b),
+            Instruction::And { x, y } => logic(x, y, quirks, |a, b| a & b),
+            Instruction::Xor { x, y } => logic(x, y, quirks, |a, b| a ^ b),
+            Instruction::AddVxVy { x, y } => Box::new(move |cpu, _| {
+                let (value, over) = cpu.read_reg(x).overflowing_add(cpu.read_reg(y));
+                cpu.write_reg(x, value);
+                cpu.write_reg(0xf, over as u8);
+            }),
+            Instruction::Sub { x, y } => Box::new(move |cpu, _| {
+                let (value, over) = cpu.read_reg(x).overflowing_sub(cpu.read_reg(y));
+                cpu.write_reg(x, value);
+                cpu.write_reg(0xf, !over as u8);
+            }),
+            Instruction::Subn { x, y } => Box::new(move |cpu, _| {
+                let (value, over) = cpu.read_reg(y).overflowing_sub(cpu.read_reg(x));
+                cpu.write_reg(x, value);
+                cpu.write_reg(0xf, !over as u8);
+            }),
+            Instruction::Shr { x, y } => {
+                let source = if quirks.shift_uses_vy { y } else { x };
+                Box::new(move |cpu, _| {
+                    let value = cpu.read_reg(source);
+                    cpu.write_reg(0xf, value & 0x1);
+                    cpu.write_reg(x, value >> 1);
+                })
+            }
+            Instruction::Shl { x, y } => {
+                let source = if quirks.shift_uses_vy { y } else { x };
+                Box::new(move |cpu, _| {
+                    let value = cpu.read_reg(source);
+                    cpu.write_reg(0xf, value >> 7);
+                    cpu.write_reg(x, value << 1);
+                })
+            }
+            Instruction::LdI(nnn) => Box::new(move |cpu, _| cpu.set_i(nnn)),
+            Instruction::AddIVx { x } => {
+                Box::new(move |cpu, _| cpu.set_i(cpu.i().wrapping_add(cpu.read_reg(x) as u16)))
+            }
+            Instruction::LdVxDt { x } => {
+                Box::new(move |cpu, _| cpu.write_reg(x, cpu.delay_timer()))
+            }
+            Instruction::Drw { x, y, n } => Box::new(move |cpu, bus| cpu.draw(bus, x, y, n)),
+            _ => Box::new(move |cpu, bus| cpu.execute(bus, instruction)),
+        }
+    }
+
+    // Anything that changes the program counter or writes to RAM has to be
+    // the last op, so invalidation happens before the next block is looked up
+    fn ends_block(instruction: Instruction) -> bool {
+        matches!(
+            instruction,
+            Instruction::Ret
+                | Instruction::Call(_)
+                | Instruction::SeVxByte { .. }
+                | Instruction::SneVxByte { .. }
+                | Instruction::SeVxVy { .. }
+                | Instruction::SneVxVy { .. }
+                | Instruction::JpV0(_)
+                | Instruction::Skp { .. }
+                | Instruction::Sknp { .. }
+                | Instruction::LdBVx { .. }
+                | Instruction::LdIVx { .. }
+        )
+    }
+
+    fn execute(&self, cpu: &mut Cpu, bus: &mut Bus) {
+        cpu.set_pc(self.end);
+        for op in &self.ops {
+            op(cpu, bus);
+        }
+    }
+}
+
+/// Skips end their block, so PC already points past the next instruction
+fn skip_if(cpu: &mut Cpu, condition: bool) {
+    if condition {
+        cpu.set_pc(cpu.pc().wrapping_add(2));
+    }
+}
+
+fn logic(x: u8, y: u8, quirks: Quirks, f: fn(u8, u8) -> u8) -> Op {
+    if quirks.logic_resets_vf {
+        Box::new(move |cpu, _| {
+            cpu.write_reg(x, f(cpu.read_reg(x), cpu.read_reg(y)));
+            cpu.write_reg(0xf, 0);
+        })
+    } else {
+        Box::new(move |cpu, _| cpu.write_reg(x, f(cpu.read_reg(x), cpu.read_reg(y))))
+    }
+}
+
+pub struct Recompiler {
+    blocks: Vec<Option<Block>>,
+    // Addresses with a block, so invalidation doesn't scan all of RAM
+    compiled: Vec<u16>,
+    code_generation: u64,
+}
+
+impl Recompiler {
+    pub fn new() -> Self {
+        Self {
+            blocks: (0..RAM_SIZE).map(|_| None).collect(),
+            compiled: Vec::new(),
+            code_generation: 0,
+        }
+    }
+
+    pub fn run(&mut self, cpu: &mut Cpu, bus: &mut Bus, cycles: usize) {
+        let mut remaining = cycles;
+        self.sync(bus);
+
+        while remaining > 0 {
+            let pc = cpu.pc() as usize;
+            if pc < RAM_SIZE && self.blocks[pc].is_none() {
+                self.blocks[pc] = Block::compile(bus, pc as u16, cpu.quirks());
+                if self.blocks[pc].is_some() {
+                    self.compiled.push(pc as u16);
+                }
+            }
+
+            let writes_ram = match self.blocks.get(pc) {
+                // Jumping to itself changes nothing however often it runs
+                Some(Some(block)) if block.ops.is_empty() && block.end as usize == pc => {
+                    remaining = 0;
+                    false
+                }
+                Some(Some(block)) if block.len <= remaining => {
+                    block.execute(cpu, bus);
+                    remaining -= block.len;
+                    block.writes_ram
+                }
+                _ => {
+                    cpu.run(bus);
+                    remaining -= 1;
+                    true
+                }
+            };
+            // Only writes from inside the loop can change code mid-run
+            if writes_ram {
+                self.sync(bus);
+            }
+        }
+    }
+
+    fn sync(&mut self, bus: &mut Bus) {
+        if bus.code_generation() != self.code_generation {
+            for address in self.compiled.drain(..) {
+                self.blocks[address as usize] = None;
+            }
+            self.code_generation = bus.code_generation();
+        }
+        if let Some(written) = bus.take_code_writes() {
+            self.invalidate(written);
+        }
+    }
+
+    /// Drops only the blocks compiled from the written bytes
+    fn invalidate(&mut self, written: Range<u16>) {
+        let blocks = &mut self.blocks;
+        self.compiled.retain(|&address| {
+            let slot = &mut blocks[address as usize];
+            if matches!(slot, Some(block) if block.overlaps(&written)) {
+                *slot = None;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fixtures::{PONG, TEST_OPCODE},
+        Backend, Chip8, Quirks,
+    };
+
+    fn assert_same_state(interpreter: &Chip8, recompiler: &Chip8) {
+        assert_eq!(interpreter.cpu.pc(), recompiler.cpu.pc());
+        assert_eq!(interpreter.cpu.i(), recompiler.cpu.i());
+        for x in 0..16 {
+            assert_eq!(interpreter.cpu.read_reg(x), recompiler.cpu.read_reg(x));
+        }
+        assert_eq!(interpreter.get_framebuffer(), recompiler.get_framebuffer());
+        for address in 0..0x1000 {
+            assert_eq!(
+                interpreter.bus.read_ram(address),
+                recompiler.bus.read_ram(address)
+            );
+        }
+    }
+
+    fn differential(program: &[u8], cycles: usize, chunk: usize) {
+        differential_with(program, cycles, chunk, Quirks::default());
+    }
+
+    fn differential_with(program: &[u8], cycles: usize, chunk: usize, quirks: Quirks) {
+        let mut interpreter = Chip8::builder()
+            .backend(Backend::Interpreter)
+            .quirks(quirks)
+            .seed(1)
+            .build();
+        let mut recompiler = Chip8::builder()
+            .backend(Backend::Recompiler)
+            .quirks(quirks)
+            .seed(1)
+            .build();
+        interpreter.set_program(program);
+        recompiler.set_program(program);
+
+        for _ in 0..cycles / chunk {
+            interpreter.run_cycles(chunk);
+            recompiler.run_cycles(chunk);
+            assert_same_state(&interpreter, &recompiler);
+        }
+    }
+
+    #[test]
+    fn test_opcode_rom() {
+        let program = TEST_OPCODE;
+
+        differential(program, 5000, 1);
+        differential(program, 5000, 7);
+        differential(program, 5000, 100);
+    }
+
+    #[test]
+    fn quirks_are_compiled_in() {
+        let program = TEST_OPCODE;
+        let quirks = Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            logic_resets_vf: true,
+        };
+
+        differential_with(program, 5000, 1, quirks);
+        differential_with(program, 5000, 100, quirks);
+    }
+
+    #[test]
+    fn pong_rom() {
+        let program = PONG;
+
+        differential(program, 20000, 1);
+        differential(program, 20000, 8);
+        differential(program, 20000, 100);
+    }
+
+    #[test]
+    fn self_modifying_code() {
+        let program = &[
+            0x60, 0x01, // 0x0200: ld v0, 0x01
+            0x70, 0x01, // 0x0202: add v0, 0x01
+            0xa2, 0x03, // 0x0204: ld I, 0x203
+            0xf0, 0x55, // 0x0206: ld [I], v0  (rewrites "add v0, 0x01")
+            0x12, 0x02, // 0x0208: jp 0x202
+        ];
+
+        differential(program, 100, 1);
+        differential(program, 100, 3);
+        differential(program, 100, 50);
+    }
+
+    #[test]
+    fn code_writes_only_drop_their_blocks() {
+        let program = &[
+            0x60, 0x01, // 0x0200: ld v0, 0x01
+            0x12, 0x04, // 0x0202: jp 0x204
+            0x12, 0x04, // 0x0204: jp 0x204
+        ];
+        let mut chip8 = Chip8::builder().backend(Backend::Recompiler).build();
+        chip8.set_program(program);
+        chip8.run_cycles(10);
+        assert!(chip8.recompiler.blocks[0x200].is_some());
+        assert!(chip8.recompiler.blocks[0x204].is_some());
+
+        chip8.bus.write_ram(&[0x60, 0x02], 0x200);
+        chip8.run_cycles(1);
+        assert!(chip8.recompiler.blocks[0x200].is_none());
+        assert!(chip8.recompiler.blocks[0x204].is_some());
+    }
+}
@@ -0,0 +1,239 @@
+const SCANLINE_BRIGHTNESS: f32 = 0.5;
+const GRID_BRIGHTNESS: f32 = 0.25;
+const CURVATURE: f32 = 0.15;
+const VIGNETTE: f32 = 0.35;
+const BLACK: u32 = 0xff000000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleFilter {
+    /// Every source pixel becomes a solid square
+    #[default]
+    Nearest,
+    /// The last row of every source pixel is dimmed
+    Scanlines,
+    /// The last row and column of every source pixel are dimmed
+    Grid,
+    /// Scanlines on a curved screen with darkened edges
+    Crt,
+}
+
+impl ScaleFilter {
+    pub const ALL: [ScaleFilter; 4] = [
+        ScaleFilter::Nearest,
+        ScaleFilter::Scanlines,
+        ScaleFilter::Grid,
+        ScaleFilter::Crt,
+    ];
+
+    pub fn next(self) -> ScaleFilter {
+        let index = Self::ALL.iter().position(|&filter| filter == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Scales ARGB images by an integer factor on the CPU
+pub struct Scaler {
+    filter: ScaleFilter,
+    factor: usize,
+    output: Vec<u32>,
+}
+
+impl Scaler {
+    pub fn new(filter: ScaleFilter, factor: usize) -> Self {
+        assert!(factor > 0, "Scale factor must be at least 1");
+
+        Self {
+            filter,
+            factor,
+            output: Vec::new(),
+        }
+    }
+
+    pub fn filter(&self) -> ScaleFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: ScaleFilter) {
+        self.filter = filter;
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        (width * self.factor, height * self.factor)
+    }
+
+    pub fn scale(&mut self, input: &[u32], width: usize, height: usize) -> &[u32] {
+        assert_eq!(input.len(), width * height);
+
+        let (out_width, out_height) = self.output_size(width, height);
+        self.output.resize(out_width * out_height, BLACK);
+
+        for y in 0..out_height {
+            for x in 0..out_width {
+                self.output[y * out_width + x] = match self.filter {
+                    ScaleFilter::Nearest => input[(y / self.factor) * width + x / self.factor],
+                    ScaleFilter::Scanlines => self.scanline(input, width, x, y),
+                    ScaleFilter::Grid => self.grid(input, width, x, y),
+                    ScaleFilter::Crt => self.crt(input, width, height, x, y),
+                };
+            }
+        }
+
+        &self.output
+    }
+
+    fn scanline(&self, input: &[u32], width: usize, x: usize, y: usize) -> u32 {
+        let color = input[(y / self.factor) * width + x / self.factor];
+        if self.is_gap(y) {
+            dim(color, SCANLINE_BRIGHTNESS)
+        } else {
+            color
+        }
+    }
+
+    fn grid(&self, input: &[u32], width: usize, x: usize, y: usize) -> u32 {
+        let color = input[(y / self.factor) * width + x / self.factor];
+        if self.is_gap(x) || self.is_gap(y) {
+            dim(color, GRID_BRIGHTNESS)
+        } else {
+            color
+        }
+    }
+
+    fn crt(&self, input: &[u32], width: usize, height: usize, x: usize, y: usize) -> u32 {
+        let (out_width, out_height) = self.output_size(width, height);
+
+        // Position relative to the centre of the screen, from -1 to 1
+        let u = (x as f32 + 0.5) / out_width as f32 * 2.0 - 1.0;
+        let v = (y as f32 + 0.5) / out_height as f32 * 2.0 - 1.0;
+
+        // Barrel distortion pushes the edges of the picture outwards
+        let curved_u = u * (1.0 + CURVATURE * v * v);
+        let curved_v = v * (1.0 + CURVATURE * u * u);
+        if curved_u.abs() > 1.0 || curved_v.abs() > 1.0 {
+            return BLACK;
+        }
+
+        let source_x = ((curved_u + 1.0) / 2.0 * out_width as f32) as usize;
+        let source_y = ((curved_v + 1.0) / 2.0 * out_height as f32) as usize;
+        let source_x = source_x.min(out_width - 1);
+        let source_y = source_y.min(out_height - 1);
+
+        let color = self.scanline(input, width, source_x, source_y);
+        let vignette = 1.0 - VIGNETTE * (u * u + v * v) / 2.0;
+        dim(color, vignette)
+    }
+
+    fn is_gap(&self, position: usize) -> bool {
+        self.factor > 1 && position % self.factor == self.factor - 1
+    }
+}
+
+fn dim(color: u32, brightness: f32) -> u32 {
+    let channel = |shift: u32| {
+        let value = ((color >> shift) & 0xff) as f32;
+        ((value * brightness).round() as u32) << shift
+    };
+
+    (color & 0xff000000) | channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: u32 = 0xffffffff;
+    const K: u32 = 0xff000000;
+    const S: u32 = 0xff808080;
+    const G: u32 = 0xff404040;
+
+    const INPUT: [u32; 2] = [W, K];
+
+    #[test]
+    fn nearest() {
+        let mut scaler = Scaler::new(ScaleFilter::Nearest, 3);
+
+        #[rustfmt::skip]
+        let expected = [
+            W, W, W, K, K, K,
+            W, W, W, K, K, K,
+            W, W, W, K, K, K,
+        ];
+        assert_eq!(scaler.output_size(2, 1), (6, 3));
+        assert_eq!(scaler.scale(&INPUT, 2, 1), &expected);
+    }
+
+    #[test]
+    fn scanlines() {
+        let mut scaler = Scaler::new(ScaleFilter::Scanlines, 3);
+
+        #[rustfmt::skip]
+        let expected = [
+            W, W, W, K, K, K,
+            W, W, W, K, K, K,
+            S, S, S, K, K, K,
+        ];
+        assert_eq!(scaler.scale(&INPUT, 2, 1), &expected);
+    }
+
+    #[test]
+    fn grid() {
+        let mut scaler = Scaler::new(ScaleFilter::Grid, 3);
+
+        #[rustfmt::skip]
+        let expected = [
+            W, W, G, K, K, K,
+            W, W, G, K, K, K,
+            G, G, G, K, K, K,
+        ];
+        assert_eq!(scaler.scale(&INPUT, 2, 1), &expected);
+    }
+
+    #[test]
+    fn factor_one_has_no_gaps() {
+        for filter in [
+            ScaleFilter::Nearest,
+            ScaleFilter::Scanlines,
+            ScaleFilter::Grid,
+        ] {
+            let mut scaler = Scaler::new(filter, 1);
+            assert_eq!(scaler.scale(&INPUT, 2, 1), &INPUT);
+        }
+    }
+
+    #[test]
+    fn crt() {
+        let mut scaler = Scaler::new(ScaleFilter::Crt, 4);
+        let input = [W; 4 * 2];
+
+        // Blue channel of a white screen: black corners, dimmed scanlines and
+        // a vignette towards the edges
+
+        #[rustfmt::skip]
+        let expected: [u8; 16 * 8] = [
+            0x00, 0xbf, 0xc8, 0xcf, 0xd4, 0xd8, 0xdb, 0xdd, 0xdd, 0xdb, 0xd8, 0xd4, 0xcf, 0xc8, 0xbf, 0x00,
+            0xc6, 0xd0, 0xd8, 0xdf, 0xe5, 0xe9, 0xec, 0xed, 0xed, 0xec, 0xe9, 0xe5, 0xdf, 0xd8, 0xd0, 0xc6,
+            0xd2, 0xdb, 0xe4, 0xeb, 0xf0, 0xf4, 0xf7, 0xf9, 0xf9, 0xf7, 0xf4, 0xf0, 0xeb, 0xe4, 0xdb, 0xd2,
+            0x6c, 0x71, 0x75, 0x79, 0x7b, 0x7d, 0x7f, 0x80, 0x80, 0x7f, 0x7d, 0x7b, 0x79, 0x75, 0x71, 0x6c,
+            0xd7, 0xe1, 0xe9, 0xf0, 0xf6, 0xfa, 0xfd, 0xfe, 0xfe, 0xfd, 0xfa, 0xf6, 0xf0, 0xe9, 0xe1, 0xd7,
+            0xd2, 0xdb, 0xe4, 0xeb, 0xf0, 0xf4, 0xf7, 0xf9, 0xf9, 0xf7, 0xf4, 0xf0, 0xeb, 0xe4, 0xdb, 0xd2,
+            0xc6, 0xd0, 0xd8, 0xdf, 0xe5, 0xe9, 0xec, 0xed, 0xed, 0xec, 0xe9, 0xe5, 0xdf, 0xd8, 0xd0, 0xc6,
+            0x00, 0x60, 0x64, 0x68, 0x6b, 0x6d, 0x6e, 0x6f, 0x6f, 0x6e, 0x6d, 0x6b, 0x68, 0x64, 0x60, 0x00,
+        ];
+        let actual: Vec<u8> = scaler
+            .scale(&input, 4, 2)
+            .iter()
+            .map(|&color| (color & 0xff) as u8)
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn next_filter() {
+        assert_eq!(ScaleFilter::Nearest.next(), ScaleFilter::Scanlines);
+        assert_eq!(ScaleFilter::Crt.next(), ScaleFilter::Nearest);
+    }
+}
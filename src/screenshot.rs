@@ -0,0 +1,108 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::Palette;
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    Io(io::Error),
+    Png(png::EncodingError),
+}
+
+impl From<io::Error> for ScreenshotError {
+    fn from(error: io::Error) -> Self {
+        ScreenshotError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(error: png::EncodingError) -> Self {
+        ScreenshotError::Png(error)
+    }
+}
+
+/// Encodes framebuffer pixels as an indexed PNG with the palette's colours,
+/// every pixel enlarged to a `scale` by `scale` square
+pub fn write_png<W: Write>(
+    writer: W,
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    palette: &Palette,
+    scale: usize,
+) -> Result<(), ScreenshotError> {
+    assert!(scale > 0, "Scale factor must be at least 1");
+
+    let (out_width, out_height) = (width * scale, height * scale);
+    let mut encoder = png::Encoder::new(writer, out_width as u32, out_height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(
+        palette
+            .colors
+            .iter()
+            .flat_map(|color| {
+                let [_, r, g, b] = color.to_be_bytes();
+                [r, g, b]
+            })
+            .collect(),
+    );
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&scale_pixels(pixels, width, scale))?;
+    Ok(())
+}
+
+/// Palette indices of the pixels, every pixel repeated `scale` times in both
+/// directions
+pub(crate) fn scale_pixels(pixels: &[u8], width: usize, scale: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(pixels.len() * scale * scale);
+    for row in pixels.chunks(width) {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&pixel| std::iter::repeat_n(pixel & 0x3, scale))
+            .collect();
+        for _ in 0..scale {
+            data.extend_from_slice(&line);
+        }
+    }
+    data
+}
+
+pub fn save_png<P: AsRef<Path>>(
+    path: P,
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    palette: &Palette,
+    scale: usize,
+) -> Result<(), ScreenshotError> {
+    let writer = BufWriter::new(File::create(path)?);
+    write_png(writer, pixels, width, height, palette, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_png_scaled() {
+        let mut buffer = Vec::new();
+        write_png(&mut buffer, &[1, 0], 2, 1, &Palette::AMBER, 2).unwrap();
+
+        let mut decoder = png::Decoder::new(buffer.as_slice());
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut image = vec![0; info.buffer_size()];
+        reader.next_frame(&mut image).unwrap();
+
+        assert_eq!((info.width, info.height), (4, 2));
+        let on = [0xff, 0xb0, 0x00];
+        let off = [0x1a, 0x0f, 0x00];
+        let row = [on, on, off, off].concat();
+        assert_eq!(image, [row.clone(), row].concat());
+    }
+}
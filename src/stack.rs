@@ -18,6 +18,11 @@ impl Stack {
         }
     }
 
+    pub fn clear(&mut self) {
+        self.data = [0; STACK_SIZE];
+        self.sp = 0;
+    }
+
     pub fn push(&mut self, item: u16) -> Result<(), StackError> {
         if (self.sp as usize) < STACK_SIZE {
             *self.data.get_mut(self.sp as usize).unwrap() = item;
@@ -0,0 +1,114 @@
+use std::io::{self, BufRead, Lines};
+
+/// What went wrong reading a line-based file, before the caller's own
+/// parsing of the line
+#[derive(Debug)]
+pub(crate) enum LineError {
+    Io(io::Error),
+    UnexpectedEof,
+    /// The line number, counting from 1
+    Parse(usize),
+}
+
+impl From<io::Error> for LineError {
+    fn from(error: io::Error) -> Self {
+        LineError::Io(error)
+    }
+}
+
+/// Lines with their numbers, counting from 1, for the movie and cheat files
+pub(crate) struct LineReader<R> {
+    lines: Lines<R>,
+    number: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            number: 0,
+        }
+    }
+
+    /// Like `next`, but the end of the file is an error
+    pub fn next_line(&mut self) -> Result<(usize, String), LineError> {
+        Ok(self.next().ok_or(LineError::UnexpectedEof)??)
+    }
+
+    /// The next line, which has to be `key` on its own or followed by
+    /// whitespace, with the key and the whitespace around the rest removed
+    pub fn expect(&mut self, key: &str) -> Result<(usize, String), LineError> {
+        let (number, line) = self.next_line()?;
+        match line.strip_prefix(key) {
+            Some(value) if value.is_empty() || value.starts_with(char::is_whitespace) => {
+                Ok((number, value.trim().to_string()))
+            }
+            _ => Err(LineError::Parse(number)),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<(usize, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        self.number += 1;
+        Some(line.map(|line| (self.number, line)))
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Two digits per byte. An odd length leaves half a byte at the end, which
+/// `get` rejects
+pub(crate) fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        let mut reader = LineReader::new("magic 1\nrom  abc \n\nlast".as_bytes());
+
+        assert_eq!(reader.expect("magic").unwrap(), (1, "1".to_string()));
+        assert_eq!(reader.expect("rom").unwrap(), (2, "abc".to_string()));
+        assert!(matches!(reader.expect("seed"), Err(LineError::Parse(3))));
+        let rest: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(rest, [(4, "last".to_string())]);
+    }
+
+    #[test]
+    fn whole_keys() {
+        let mut reader = LineReader::new("romfoo\nseeds 5\nquirks\nfont\t01".as_bytes());
+
+        assert!(matches!(reader.expect("rom"), Err(LineError::Parse(1))));
+        assert!(matches!(reader.expect("seed"), Err(LineError::Parse(2))));
+        assert_eq!(reader.expect("quirks").unwrap(), (3, String::new()));
+        assert_eq!(reader.expect("font").unwrap(), (4, "01".to_string()));
+    }
+
+    #[test]
+    fn unexpected_eof() {
+        let mut reader = LineReader::new("magic 1\n".as_bytes());
+        reader.expect("magic").unwrap();
+
+        assert!(matches!(reader.next_line(), Err(LineError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(to_hex(&[0x01, 0xab]), "01ab");
+        assert_eq!(parse_hex("01ab"), Some(vec![0x01, 0xab]));
+        assert_eq!(parse_hex("01a"), None);
+        assert_eq!(parse_hex("0g"), None);
+    }
+}
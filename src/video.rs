@@ -0,0 +1,318 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, Write},
+    path::Path,
+};
+
+use crate::{screenshot::scale_pixels, Audio, Palette, Tone, WavAudio, FRAME_RATE};
+
+const SAMPLE_RATE: u32 = 44100;
+
+#[derive(Debug)]
+pub enum VideoError {
+    Io(io::Error),
+    Gif(gif::EncodingError),
+    Wav(hound::Error),
+    UnknownFormat,
+}
+
+impl From<io::Error> for VideoError {
+    fn from(error: io::Error) -> Self {
+        VideoError::Io(error)
+    }
+}
+
+impl From<gif::EncodingError> for VideoError {
+    fn from(error: gif::EncodingError) -> Self {
+        VideoError::Gif(error)
+    }
+}
+
+impl From<hound::Error> for VideoError {
+    fn from(error: hound::Error) -> Self {
+        VideoError::Wav(error)
+    }
+}
+
+/// Writes frames as an animated GIF. Identical consecutive frames are merged
+/// into one frame with a longer delay
+pub struct GifRecorder<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: usize,
+    scale: usize,
+    pending: Option<Vec<u8>>,
+    pending_start: u64,
+    frames: u64,
+}
+
+impl<W: Write> GifRecorder<W> {
+    pub fn new(
+        writer: W,
+        width: usize,
+        height: usize,
+        palette: &Palette,
+        scale: usize,
+    ) -> Result<Self, VideoError> {
+        let colors: Vec<u8> = palette
+            .colors
+            .iter()
+            .flat_map(|color| {
+                let [_, r, g, b] = color.to_be_bytes();
+                [r, g, b]
+            })
+            .collect();
+
+        let (out_width, out_height) = ((width * scale) as u16, (height * scale) as u16);
+        let mut encoder = gif::Encoder::new(writer, out_width, out_height, &colors)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(Self {
+            encoder,
+            width,
+            scale,
+            pending: None,
+            pending_start: 0,
+            frames: 0,
+        })
+    }
+
+    pub fn record_frame(&mut self, pixels: &[u8]) -> Result<(), VideoError> {
+        let data = scale_pixels(pixels, self.width, self.scale);
+        if self.pending.as_ref() != Some(&data) {
+            self.write_pending()?;
+            self.pending = Some(data);
+            self.pending_start = self.frames;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, VideoError> {
+        self.write_pending()?;
+        Ok(self.encoder.into_inner()?)
+    }
+
+    fn write_pending(&mut self) -> Result<(), VideoError> {
+        if let Some(data) = self.pending.take() {
+            // GIF delays are in centiseconds. Rounding the start and end time
+            // instead of the length keeps the animation from drifting
+            let delay = centiseconds(self.frames) - centiseconds(self.pending_start);
+            let width = (self.width * self.scale) as u16;
+            let height = (data.len() / (self.width * self.scale)) as u16;
+
+            let mut frame = gif::Frame::from_indexed_pixels(width, height, &data, None);
+            frame.delay = delay.min(u16::MAX as u64) as u16;
+            self.encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+fn centiseconds(frames: u64) -> u64 {
+    let frame_rate = FRAME_RATE as u64;
+    (frames * 100 + frame_rate / 2) / frame_rate
+}
+
+/// Writes frames as uncompressed 4:4:4 Y4M video at 60 fps, with the beep in
+/// a WAV file next to it
+pub struct Y4mRecorder<V: Write, A: Write + Seek> {
+    video: V,
+    audio: WavAudio<A>,
+    width: usize,
+    scale: usize,
+    colors: [[u8; 3]; 4],
+}
+
+impl<V: Write, A: Write + Seek> Y4mRecorder<V, A> {
+    pub fn new(
+        mut video: V,
+        audio: A,
+        width: usize,
+        height: usize,
+        palette: &Palette,
+        scale: usize,
+        tone: Tone,
+    ) -> Result<Self, VideoError> {
+        writeln!(
+            video,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=FULL",
+            width * scale,
+            height * scale,
+            FRAME_RATE
+        )?;
+
+        let audio = WavAudio::new(audio, tone, SAMPLE_RATE)?;
+
+        let mut colors = [[0; 3]; 4];
+        for (yuv, &color) in colors.iter_mut().zip(&palette.colors) {
+            *yuv = rgb_to_yuv(color);
+        }
+
+        Ok(Self {
+            video,
+            audio,
+            width,
+            scale,
+            colors,
+        })
+    }
+
+    pub fn record_frame(&mut self, pixels: &[u8], beep: bool) -> Result<(), VideoError> {
+        let data = scale_pixels(pixels, self.width, self.scale);
+
+        self.video.write_all(b"FRAME\n")?;
+        for plane in 0..3 {
+            let plane: Vec<u8> = data
+                .iter()
+                .map(|&pixel| self.colors[pixel as usize][plane])
+                .collect();
+            self.video.write_all(&plane)?;
+        }
+
+        self.audio.set_sound_on(beep);
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), VideoError> {
+        self.video.flush()?;
+        self.audio.finish()?;
+        Ok(())
+    }
+}
+
+/// Full range BT.601, as used by JPEG
+fn rgb_to_yuv(color: u32) -> [u8; 3] {
+    let [_, r, g, b] = color.to_be_bytes();
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+    let v = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+
+    [y, u, v].map(|value| value.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Records to a GIF or a Y4M + WAV pair, picked by the file extension. The
+/// tone is only used for the WAV file
+pub enum VideoRecorder {
+    Gif(GifRecorder<BufWriter<File>>),
+    Y4m(Y4mRecorder<BufWriter<File>, BufWriter<File>>),
+}
+
+impl VideoRecorder {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: usize,
+        height: usize,
+        palette: &Palette,
+        scale: usize,
+        tone: Tone,
+    ) -> Result<Self, VideoError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+
+        match extension {
+            Some("gif") => {
+                let writer = BufWriter::new(File::create(path)?);
+                let recorder = GifRecorder::new(writer, width, height, palette, scale)?;
+                Ok(VideoRecorder::Gif(recorder))
+            }
+            Some("y4m") => {
+                let video = BufWriter::new(File::create(path)?);
+                let audio = BufWriter::new(File::create(path.with_extension("wav"))?);
+                let recorder = Y4mRecorder::new(video, audio, width, height, palette, scale, tone)?;
+                Ok(VideoRecorder::Y4m(recorder))
+            }
+            _ => Err(VideoError::UnknownFormat),
+        }
+    }
+
+    pub fn record_frame(&mut self, pixels: &[u8], beep: bool) -> Result<(), VideoError> {
+        match self {
+            VideoRecorder::Gif(recorder) => recorder.record_frame(pixels),
+            VideoRecorder::Y4m(recorder) => recorder.record_frame(pixels, beep),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), VideoError> {
+        match self {
+            VideoRecorder::Gif(recorder) => recorder.finish()?.flush()?,
+            VideoRecorder::Y4m(recorder) => recorder.finish()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn gif_deduplicates_frames() {
+        let mut recorder = GifRecorder::new(Vec::new(), 2, 1, &Palette::CLASSIC, 2).unwrap();
+        for pixels in [[1, 0], [1, 0], [1, 0], [0, 1], [1, 0]] {
+            recorder.record_frame(&pixels).unwrap();
+        }
+        let buffer = recorder.finish().unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(buffer.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (4, 2));
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((frame.delay, frame.buffer.to_vec()));
+        }
+        assert_eq!(
+            frames,
+            vec![
+                (5, vec![1, 1, 0, 0, 1, 1, 0, 0]),
+                (2, vec![0, 0, 1, 1, 0, 0, 1, 1]),
+                (1, vec![1, 1, 0, 0, 1, 1, 0, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn y4m_and_wav() {
+        let mut video = Vec::new();
+        let mut audio = Cursor::new(Vec::new());
+        let mut recorder = Y4mRecorder::new(
+            &mut video,
+            &mut audio,
+            2,
+            1,
+            &Palette::CLASSIC,
+            1,
+            Tone::default(),
+        )
+        .unwrap();
+        recorder.record_frame(&[1, 0], false).unwrap();
+        recorder.record_frame(&[0, 1], true).unwrap();
+        recorder.finish().unwrap();
+
+        let header = b"YUV4MPEG2 W2 H1 F60:1 Ip A1:1 C444 XCOLORRANGE=FULL\n";
+        let frames = [
+            b"FRAME\n".as_ref(),
+            &[255, 0, 128, 128, 128, 128],
+            b"FRAME\n",
+            &[0, 255, 128, 128, 128, 128],
+        ]
+        .concat();
+        assert_eq!(video, [header.as_ref(), &frames].concat());
+
+        audio.set_position(0);
+        let samples: Vec<i16> = hound::WavReader::new(audio)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        let samples_per_frame = (SAMPLE_RATE / FRAME_RATE) as usize;
+        assert_eq!(samples.len(), 2 * samples_per_frame);
+        assert!(samples[..samples_per_frame].iter().all(|&s| s == 0));
+        assert!(samples[samples_per_frame..].iter().any(|&s| s != 0));
+    }
+}